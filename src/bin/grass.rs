@@ -1,8 +1,9 @@
 use bevy::prelude::*;
 use bevy_editor_pls::EditorPlugin;
-use shader_playground::generator::standard_generator::Plane;
+use shader_playground::generator::standard_generator::{Plane, Union};
 use shader_playground::generator::GrassGenerator;
 use shader_playground::generator::StandardGeneratorConfig;
+use shader_playground::grass::GrassChunking;
 use shader_playground::plugin::GrassPlugin;
 use shader_playground::GrassBundle;
 
@@ -30,20 +31,28 @@ fn setup_grass(mut commands: Commands) {
         height: 3.,
         height_deviation: 0.5,
         seed: Some(0x121),
+        ..default()
     };
-    // translation indicates the outer point
-    let plane1 = Plane {
-        dimensions: Transform::from_xyz(30., 0., 10.),
-    };
-    let plane2 = Plane {
-        dimensions: Transform::from_xyz(10., 2., -10.),
-    };
+    // the translation is the center of the plane
+    let plane1 = Plane::new(Vec2::new(30., 10.)).with_transform(Transform::from_xyz(15., 0., 5.));
+    // a slope rising by 2 along the x axis, half of it lies on the first plane
+    let plane2 = Plane::new(Vec2::new(10., 10.))
+        .with_transform(Transform::from_xyz(5., 1., 0.))
+        .with_height(|point| point.x / 5.);
 
-    let mut grass = plane1.generate_grass(config.clone());
-    grass
-        .instances
-        .extend(plane2.generate_grass(config).instances);
-    commands.spawn((GrassBundle { grass, ..default() },));
+    // the union doesn't place blades of the second plane where the first one already has grass
+    let grass = Union(plane1, plane2).generate_grass(config);
+    // split the grass into chunks, so the parts outside of the view can be culled
+    commands.spawn((
+        GrassBundle { grass, ..default() },
+        GrassChunking { cell_size: 5. },
+    ));
+
+    // the grass is lit by the lights of the scene
+    commands.spawn(DirectionalLightBundle {
+        transform: Transform::from_xyz(0., 10., 0.).looking_at(Vec3::new(4., 0., 3.), Vec3::Y),
+        ..default()
+    });
 
     // camera
     commands.spawn(Camera3dBundle {
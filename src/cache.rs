@@ -8,10 +8,60 @@ pub struct GrassCache {
     pub data: HashMap<Entity, CachedGrassChunk>,
 }
 
+/// A chunk of grass in the render world, which is removed when its entity is despawned or hidden
 #[derive(Debug, Default)]
 pub struct CachedGrassChunk {
     pub grass: Grass,
-    pub uniform_bind_ground: Option<BindGroup>,
     pub buffer: Option<Buffer>,
     pub transform: GlobalTransform,
+    /// Marks chunks whose instances have to be uploaded to the [`buffer`](Self::buffer) again
+    pub dirty: bool,
+}
+
+/// The maximum number of unused instance buffers kept in the [`GrassBufferPool`]
+const MAX_POOLED_BUFFERS: usize = 256;
+
+/// Instance buffers of removed chunks, which are reused for new chunks instead of allocating new ones
+#[derive(Resource, DerefMut, Deref, Debug, Default)]
+pub struct GrassBufferPool {
+    pub buffers: Vec<Buffer>,
+}
+
+impl GrassBufferPool {
+    /// Stores an unused buffer for later use
+    pub fn recycle(&mut self, buffer: Buffer) {
+        self.buffers.push(buffer);
+        if self.buffers.len() > MAX_POOLED_BUFFERS {
+            // drop the smallest buffer, since it is the least likely to fit
+            if let Some((smallest, _)) = self
+                .buffers
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, buffer)| buffer.size())
+            {
+                self.buffers.swap_remove(smallest);
+            }
+        }
+    }
+    /// Takes the smallest buffer which can hold `size` bytes
+    pub fn take(&mut self, size: u64) -> Option<Buffer> {
+        let (index, _) = self
+            .buffers
+            .iter()
+            .enumerate()
+            .filter(|(_, buffer)| buffer.size() >= size)
+            .min_by_key(|(_, buffer)| buffer.size())?;
+        Some(self.buffers.swap_remove(index))
+    }
+}
+
+/// The bind group of the [`RegionConfig`](crate::RegionConfig) shared by all chunks.
+///
+/// It is kept outside of the [`GrassCache`], so chunks spawned at any time can use it
+#[derive(Resource, Debug, Default)]
+pub struct GrassUniformBindGroup {
+    pub bind_group: Option<BindGroup>,
+    /// The buffer of the [`GrassDisplacer`](crate::displacement::GrassDisplacer)s,
+    /// which is rewritten every frame and shared by all bind groups
+    pub displacer_buffer: Option<Buffer>,
 }
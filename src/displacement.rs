@@ -0,0 +1,44 @@
+use bevy::prelude::*;
+
+/// The maximum number of [`GrassDisplacer`]s affecting the grass at the same time.
+///
+/// If more displacers exist, only the ones closest to a camera are used
+pub const MAX_GRASS_DISPLACERS: usize = 64;
+
+/// Pushes the grass blades around the entity away, for example for players, vehicles or projectiles.
+///
+/// The displacer is placed at the [`GlobalTransform`] of the entity
+/// and affects all blades with their roots inside of its radius.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub struct GrassDisplacer {
+    /// The radius around the entity in which blades are pushed away
+    pub radius: f32,
+    /// How far the blade tips at the center are pushed per unit of blade height.
+    ///
+    /// The push fades out towards the radius
+    pub strength: f32,
+}
+
+impl Default for GrassDisplacer {
+    fn default() -> Self {
+        GrassDisplacer {
+            radius: 1.,
+            strength: 1.,
+        }
+    }
+}
+
+/// A [`GrassDisplacer`] in the render world
+#[derive(Clone, Copy, Debug)]
+pub struct ExtractedGrassDisplacer {
+    pub position: Vec3,
+    pub radius: f32,
+    pub strength: f32,
+}
+
+/// The displacers used in the current frame, at most [`MAX_GRASS_DISPLACERS`]
+#[derive(Resource, Deref, DerefMut, Debug, Default)]
+pub struct ExtractedGrassDisplacers {
+    pub displacers: Vec<ExtractedGrassDisplacer>,
+}
@@ -1,20 +1,101 @@
-use crate::cache::GrassCache;
+use crate::cache::{GrassBufferPool, GrassCache};
+use crate::displacement::{
+    ExtractedGrassDisplacer, ExtractedGrassDisplacers, GrassDisplacer, MAX_GRASS_DISPLACERS,
+};
 use crate::grass::Grass;
 use bevy::prelude::*;
 use bevy::render::Extract;
 
+/// Keeps the [`GrassCache`] in sync with the grass entities of the main world.
+///
+/// Chunks are added when they are spawned or shown and updated when their [`Grass`] changes.
+/// Despawned and hidden chunks are removed, so their instance buffers can be reused.
+/// Chunks outside of the camera view stay cached, since they are culled by the mesh extraction.
 pub fn extract_grass(
     grass_query: Extract<
-        Query<(Entity, &Grass, &GlobalTransform, &ComputedVisibility), Changed<Grass>>,
+        Query<(
+            Entity,
+            &Grass,
+            ChangeTrackers<Grass>,
+            &GlobalTransform,
+            &ComputedVisibility,
+        )>,
     >,
     mut grass_cache: ResMut<GrassCache>,
+    mut buffer_pool: ResMut<GrassBufferPool>,
 ) {
-    for (entity, grass, transform, visibility) in grass_query.iter() {
-        if !visibility.is_visible() {
+    // remove despawned and hidden chunks, keeping their buffers for new chunks
+    let removed: Vec<Entity> = grass_cache
+        .keys()
+        .filter(|entity| match grass_query.get(**entity) {
+            Ok((.., visibility)) => !visibility.is_visible_in_hierarchy(),
+            Err(_) => true,
+        })
+        .copied()
+        .collect();
+    for entity in removed {
+        if let Some(buffer) = grass_cache.remove(&entity).and_then(|chunk| chunk.buffer) {
+            buffer_pool.recycle(buffer);
+        }
+    }
+
+    for (entity, grass, grass_tracker, transform, visibility) in grass_query.iter() {
+        if !visibility.is_visible_in_hierarchy() {
             continue;
         }
-        let cache_value = grass_cache.entry(entity).or_default();
-        cache_value.grass = grass.clone();
-        cache_value.transform = *transform;
+        // only touch the cache when something changed, so the buffers aren't prepared every frame
+        let moved = match grass_cache.get(&entity) {
+            Some(cached) if !grass_tracker.is_changed() => cached.transform != *transform,
+            _ => {
+                let cache_value = grass_cache.entry(entity).or_default();
+                cache_value.grass = grass.clone();
+                cache_value.transform = *transform;
+                cache_value.dirty = true;
+                false
+            }
+        };
+        if moved {
+            if let Some(cache_value) = grass_cache.get_mut(&entity) {
+                cache_value.transform = *transform;
+            }
+        }
+    }
+}
+
+/// Collects the [`GrassDisplacer`]s of the current frame.
+///
+/// Past [`MAX_GRASS_DISPLACERS`], the displacers closest to an active camera are kept,
+/// since the grass reacting far away is the least noticeable.
+pub fn extract_grass_displacers(
+    displacer_query: Extract<Query<(&GrassDisplacer, &GlobalTransform)>>,
+    camera_query: Extract<Query<(&Camera, &GlobalTransform)>>,
+    mut extracted_displacers: ResMut<ExtractedGrassDisplacers>,
+) {
+    extracted_displacers.clear();
+    extracted_displacers.extend(
+        displacer_query
+            .iter()
+            .filter(|(displacer, _)| displacer.radius > 0. && displacer.strength != 0.)
+            .map(|(displacer, transform)| ExtractedGrassDisplacer {
+                position: transform.translation(),
+                radius: displacer.radius,
+                strength: displacer.strength,
+            }),
+    );
+    if extracted_displacers.len() <= MAX_GRASS_DISPLACERS {
+        return;
     }
+    let cameras: Vec<Vec3> = camera_query
+        .iter()
+        .filter(|(camera, _)| camera.is_active)
+        .map(|(_, transform)| transform.translation())
+        .collect();
+    let camera_distance = |displacer: &ExtractedGrassDisplacer| {
+        cameras
+            .iter()
+            .map(|camera| camera.distance_squared(displacer.position))
+            .fold(f32::MAX, f32::min)
+    };
+    extracted_displacers.sort_by(|a, b| camera_distance(a).total_cmp(&camera_distance(b)));
+    extracted_displacers.truncate(MAX_GRASS_DISPLACERS);
 }
@@ -1,39 +1,303 @@
+pub mod budget;
+pub mod color;
+pub mod combinator;
+pub mod distribution;
+pub mod exclusion;
+pub mod field;
+pub mod height;
+pub mod heightmap;
+pub mod mask;
+pub mod mesh;
+pub mod noise;
 pub mod plane;
+pub mod polygon;
+pub mod radial;
+pub mod rules;
+pub mod task;
+mod texture;
+use crate::grass::GrassBlade;
 use crate::Grass;
+use bevy::prelude::{Vec2, Vec3};
+use budget::BladeBudget;
+use color::ColorVariation;
+use distribution::Distribution;
+use exclusion::Exclusion;
+use field::ScalarField;
+use height::HeightDistribution;
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use rules::PlacementRules;
+use std::f32::consts::TAU;
+use std::sync::Arc;
 
 pub mod standard_generator {
+    pub use super::budget::BladeBudget;
+    pub use super::color::ColorVariation;
+    pub use super::combinator::{Intersect, Subtract, Thinned, Transformed, Union};
+    pub use super::distribution::Distribution;
+    pub use super::exclusion::Exclusion;
+    pub use super::field::{ScalarField, TextureField};
+    pub use super::height::HeightDistribution;
+    pub use super::heightmap::Heightmap;
+    pub use super::mask::DensityMask;
+    pub use super::mesh::MeshSurface;
+    pub use super::noise::{NoiseField, NoiseKind};
     pub use super::plane::Plane;
+    pub use super::polygon::Polygon;
+    pub use super::radial::{Annulus, Disc, Falloff, Sector};
+    pub use super::rules::PlacementRules;
+    pub use super::task::{GrassGenerated, GrassGenerationProgress, GrassGenerationTask};
+    pub use super::CombinableConfig;
+    pub use super::GrassArea;
     pub use super::GrassGenerator;
     pub use super::StandardGeneratorConfig;
 }
+/// A config which can be passed on by the [combinators](combinator) to their generators.
+///
+/// Settings which apply to a whole generator call, like the [`BladeBudget`],
+/// have to be applied once to the combined grass instead of to each generator.
+/// Custom configs without such settings can use the default implementation.
+pub trait CombinableConfig: Clone {
+    /// Runs `generate` for a whole combinator call, `generate` passes its config on to the generators
+    fn combine(self, generate: impl Fn(Self) -> Grass) -> Grass {
+        generate(self)
+    }
+}
+
+impl CombinableConfig for StandardGeneratorConfig {
+    fn combine(self, generate: impl Fn(Self) -> Grass) -> Grass {
+        self.within_budget(None, generate)
+    }
+}
+
+/// The separate streams of random numbers derived from [`StandardGeneratorConfig::seed`],
+/// so removing or trimming blades doesn't repeat the random numbers of the generator
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum RandomStream {
+    Budget = 1,
+    Thinning,
+    Look,
+}
+
+/// Mixes `value` into `seed`, so similar inputs like neighbouring cells get unrelated seeds
+pub(crate) fn mix_seed(seed: u64, value: u64) -> u64 {
+    hash_u64(seed ^ hash_u64(value))
+}
+
+/// The splitmix64 finalizer
+fn hash_u64(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
 pub trait GrassGenerator<Config> {
     fn generate_grass(&self, generator_config: Config) -> Grass;
 }
+
+impl<Config, G: GrassGenerator<Config> + ?Sized> GrassGenerator<Config> for Arc<G> {
+    fn generate_grass(&self, generator_config: Config) -> Grass {
+        self.as_ref().generate_grass(generator_config)
+    }
+}
+
+/// A shape covering an area, which is used by the [combinators](combinator) to remove overlapping blades
+pub trait GrassArea {
+    /// Checks if a blade at `position` lies inside of the area
+    fn contains(&self, position: Vec3) -> bool;
+}
+
+impl<G: GrassArea + ?Sized> GrassArea for Arc<G> {
+    fn contains(&self, position: Vec3) -> bool {
+        self.as_ref().contains(position)
+    }
+}
 #[derive(Debug, Clone)]
 pub struct StandardGeneratorConfig {
     /// Density of the grass generated.
     /// The higher the density the more grass will be spawned per area
     pub density: f32,
+    /// An optional number of blades the generator should create, which replaces or caps the `density`.
+    ///
+    /// The density is scaled to the area of each generator call, so resizing an area keeps the blade count.
+    /// Combinators apply the budget once to their combined grass
+    pub blade_budget: Option<BladeBudget>,
+    /// How the grass blades are spread over the area of the generator
+    pub distribution: Distribution,
     /// The mean height of the grass blades created by the generator
     pub height: f32,
     /// The deviation of the blade heights.
     ///
     /// If you want no deviation in your blades, you can set it to 0
     pub height_deviation: f32,
-    /// The seed used for the random number generator, which calculates height, x and z coordinates of the grass blades
+    /// An optional distribution of the blade heights.
+    ///
+    /// If [None] is used, the heights are spread uniformly over `height` ± `height_deviation`
+    pub height_distribution: Option<HeightDistribution>,
+    /// The deviation of the blade width scale around 1
+    pub width_deviation: f32,
+    /// The mean amount the blades lean into a random direction,
+    /// as horizontal offset of the tip per unit of height
+    pub lean: f32,
+    /// The deviation of the lean amount
+    pub lean_deviation: f32,
+    /// How the color tint of the blades varies
+    pub color_variation: ColorVariation,
+    /// An optional field which scales the blade heights depending on their position.
+    ///
+    /// Texture fields return values between 0 and 1, so a white texel keeps the full height
+    pub height_field: Option<ScalarField>,
+    /// The seed used for the random number generator and noise fields, which calculate height, x and z coordinates of the grass blades
     ///
     /// If you want the grass to look always the same you can set a seed.
     /// If [None] is used, the seed is calculated from the internal random generator of the running OS
+    /// and noise fields without a seed of their own use 0
     pub seed: Option<u64>,
+    /// An optional field which scales the local density of the grass.
+    ///
+    /// The sampled values are the probability between 0 and 1 of a blade being placed, for example from a [`DensityMask`](mask::DensityMask).
+    /// If [None] is used, the grass covers the whole area of the generator
+    pub density_mask: Option<ScalarField>,
+    /// Areas where no grass is placed, like houses or roads
+    pub exclusions: Vec<Exclusion>,
+    /// Rules removing grass by the slope and altitude of the surface
+    pub placement_rules: PlacementRules,
+    /// Lets the blades grow perpendicular to the surface instead of straight up.
+    ///
+    /// This is needed for grass on walls, cliffs or spherical planets.
+    /// Disabled by default, so grass on uneven terrain keeps growing straight up
+    pub align_to_surface: bool,
 }
 
 impl Default for StandardGeneratorConfig {
     fn default() -> Self {
         Self {
             density: 20.,
+            blade_budget: None,
+            distribution: Distribution::Uniform,
             height: 2.,
             height_deviation: 0.5,
+            height_distribution: None,
+            height_field: None,
+            width_deviation: 0.,
+            lean: 0.,
+            lean_deviation: 0.,
+            color_variation: ColorVariation::None,
             seed: None,
+            density_mask: None,
+            exclusions: Vec::new(),
+            placement_rules: PlacementRules::default(),
+            align_to_surface: false,
+        }
+    }
+}
+
+impl StandardGeneratorConfig {
+    /// Creates the random number generator used for one generation run
+    pub(crate) fn rng(&self) -> SmallRng {
+        if let Some(seed) = self.seed {
+            SmallRng::seed_from_u64(seed)
+        } else {
+            SmallRng::from_entropy()
+        }
+    }
+    /// Creates the random number generator of a separate `stream`, which is reproducible like [`rng`](Self::rng)
+    pub(crate) fn stream_rng(&self, stream: RandomStream) -> SmallRng {
+        match self.seed {
+            Some(seed) => SmallRng::seed_from_u64(mix_seed(seed, stream as u64)),
+            None => SmallRng::from_entropy(),
+        }
+    }
+    /// The seed of noise fields without a seed of their own
+    fn field_seed(&self) -> u64 {
+        self.seed.unwrap_or(0)
+    }
+    /// Gives all noise fields without a seed of their own the fixed `seed`,
+    /// so they stay the same when the seed of the config changes
+    pub(crate) fn fix_field_seeds(&mut self, seed: u64) {
+        let fields = [self.density_mask.as_mut(), self.height_field.as_mut()];
+        for field in fields.into_iter().flatten() {
+            field.fix_seed(seed);
+        }
+        if let ColorVariation::Field { field, .. } = &mut self.color_variation {
+            field.fix_seed(seed);
+        }
+    }
+    /// Runs `generate` with the density scaled to the [`blade_budget`](Self::blade_budget), if one is set.
+    ///
+    /// Every generator should wrap the creation of its blades with this and pass the `area` it fills
+    /// with the density, if it is known.
+    /// `generate` receives the config without the budget, so nested generators don't apply it again
+    pub(crate) fn within_budget(
+        mut self,
+        area: Option<f32>,
+        generate: impl Fn(Self) -> Grass,
+    ) -> Grass {
+        match self.blade_budget.take() {
+            Some(budget) => budget.fit(self, area, generate),
+            None => generate(self),
+        }
+    }
+    /// Checks if `position` lies inside one of the [`exclusions`](Self::exclusions).
+    ///
+    /// Every generator should call this as soon as the root position of a candidate is known,
+    /// before measuring the surface or drawing random numbers for it
+    pub(crate) fn excludes(&self, position: Vec3) -> bool {
+        self.exclusions
+            .iter()
+            .any(|exclusion| exclusion.contains(position))
+    }
+    /// Decides whether a blade is placed at `position` on a surface facing `normal`.
+    ///
+    /// Every generator should call this for each candidate position outside of the exclusions before creating the blade
+    pub(crate) fn accepts(&self, position: Vec3, normal: Vec3, rand: &mut SmallRng) -> bool {
+        if self.density_mask.is_none() && self.placement_rules.is_empty() {
+            return true;
+        }
+        let mut density = self.placement_rules.density(position, normal);
+        if let Some(mask) = &self.density_mask {
+            density *= mask.sample(position, self.field_seed());
+        }
+        // always draw, so the blades after this one keep their random numbers whatever the density is
+        rand.gen::<f32>() < density
+    }
+    /// Creates a blade at `position` on a surface facing `normal`, with a random height, facing, width, lean and color.
+    ///
+    /// The height is drawn from `rand` like the position, the other attributes from `look`,
+    /// which every generator creates with [`RandomStream::Look`].
+    /// This way the attributes don't shift the positions and heights of the following blades
+    pub(crate) fn blade(
+        &self,
+        position: Vec3,
+        normal: Vec3,
+        rand: &mut SmallRng,
+        look: &mut SmallRng,
+    ) -> GrassBlade {
+        let mut height = match &self.height_distribution {
+            Some(distribution) => distribution.sample(rand),
+            None => {
+                let height_deviation = (rand.gen::<f32>() - 0.5) * 2. * self.height_deviation;
+                self.height + height_deviation
+            }
+        };
+        if let Some(field) = &self.height_field {
+            height *= field.sample(position, self.field_seed()).max(0.);
+        }
+        let rotation = look.gen::<f32>() * TAU;
+        let width = 1. + (look.gen::<f32>() - 0.5) * 2. * self.width_deviation;
+        let lean_amount = self.lean + (look.gen::<f32>() - 0.5) * 2. * self.lean_deviation;
+        let lean_direction = look.gen::<f32>() * TAU;
+        GrassBlade {
+            position,
+            height,
+            rotation,
+            width: width.max(0.),
+            lean: Vec2::new(lean_direction.cos(), lean_direction.sin()) * lean_amount.max(0.),
+            color: self.color_variation.tint(position, self.field_seed(), look),
+            up: if self.align_to_surface {
+                normal.try_normalize().unwrap_or(Vec3::Y)
+            } else {
+                Vec3::Y
+            },
         }
     }
 }
@@ -0,0 +1,333 @@
+use super::{RandomStream, StandardGeneratorConfig};
+use crate::Grass;
+use rand::seq::index;
+
+/// A limit on the number of blades created by one generator call.
+///
+/// Instead of using [`StandardGeneratorConfig::density`] directly, the generators scale the density
+/// to the area they cover, so the blade count stays the same when the area is resized.
+/// The count is measured on the final grass of the call, after all filters like exclusions,
+/// density masks and placement rules and after combining the grass of [combinators](super::combinator).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BladeBudget {
+    /// Creates exactly this many blades, as long as the area has space for them.
+    ///
+    /// The configured density is ignored
+    Exact(usize),
+    /// Uses the configured density, but never creates more than this many blades
+    Max(usize),
+}
+
+/// How often the generator is rerun with a corrected density
+const MAX_RUNS: usize = 5;
+/// The extra share of blades aimed at for exact budgets, so the corrected run rarely falls short
+const OVERSHOOT: f32 = 1.05;
+/// The share of blades above the aimed count which is trimmed without rerunning the generator.
+///
+/// Trimming more would visibly thin out regular distributions like grids and poisson disks
+const MAX_TRIM: f32 = 1.2;
+/// The density of the first run for exact budgets, if neither the area nor a density is known
+const PROBE_DENSITY: f32 = 1.;
+/// The density increase after a run created no blades, which gives nothing to measure.
+///
+/// If the increased run creates no blades either, the filters remove everything and the grass stays empty
+const EMPTY_RUN_GROWTH: f32 = 16.;
+/// The largest factor the density of the first run is increased by,
+/// so filters removing nearly every blade can't make the distributions allocate huge amounts of points
+const MAX_DENSITY_GROWTH: f32 = 256.;
+
+impl BladeBudget {
+    /// The number of blades which shouldn't be exceeded
+    pub fn count(&self) -> usize {
+        match *self {
+            BladeBudget::Exact(count) | BladeBudget::Max(count) => count,
+        }
+    }
+    /// The number of blades the density is scaled to.
+    ///
+    /// Exact budgets aim a bit higher, since the count of random distributions varies around the aim
+    fn aim(&self) -> f32 {
+        match *self {
+            BladeBudget::Exact(target) => {
+                let target = target as f32;
+                (target * OVERSHOOT).max(target + 2. * target.sqrt())
+            }
+            BladeBudget::Max(target) => target as f32,
+        }
+    }
+    /// Checks if `count` blades satisfy the budget after trimming the blades above it
+    fn is_met(&self, count: usize) -> bool {
+        let trimmable = count as f32 <= self.aim() * MAX_TRIM;
+        match *self {
+            BladeBudget::Exact(target) => count >= target && trimmable,
+            BladeBudget::Max(_) => trimmable,
+        }
+    }
+    /// The density of the first run on an `area`, if it is known
+    fn initial_density(&self, density: f32, area: Option<f32>) -> f32 {
+        let density = density.max(0.);
+        match (*self, area) {
+            (BladeBudget::Exact(_), Some(area)) if area > 0. => self.aim() / area,
+            (BladeBudget::Exact(_), _) if density <= 0. => PROBE_DENSITY,
+            (BladeBudget::Max(_), Some(area)) if area > 0. => density.min(self.aim() / area),
+            _ => density,
+        }
+    }
+    /// Runs `generate` with a density scaled to fit the budget.
+    ///
+    /// The first run uses the density which creates the budget on `area`.
+    /// If the area is unknown or filters remove blades, the following runs correct the density
+    /// by the ratio between the aimed and the measured count, up to [`MAX_DENSITY_GROWTH`] times the first density.
+    /// Blades above the budget are removed randomly, so the distribution keeps its look.
+    pub(crate) fn fit(
+        &self,
+        mut generator_config: StandardGeneratorConfig,
+        area: Option<f32>,
+        generate: impl Fn(StandardGeneratorConfig) -> Grass,
+    ) -> Grass {
+        let target = self.count();
+        if target == 0 {
+            return Grass::default();
+        }
+        generator_config.density = self.initial_density(generator_config.density, area);
+        let max_density = generator_config.density * MAX_DENSITY_GROWTH;
+        let is_short = |grass: &Grass| match self {
+            BladeBudget::Exact(_) => grass.instances.len() < target,
+            BladeBudget::Max(_) => false,
+        };
+        let mut grass = generate(generator_config.clone());
+        // the last run with enough blades, in case the corrected run falls short
+        let mut fallback = None;
+        let mut empty_runs = 0;
+        for _ in 1..MAX_RUNS {
+            let count = grass.instances.len();
+            if self.is_met(count) {
+                break;
+            }
+            let growth = if count == 0 {
+                empty_runs += 1;
+                if empty_runs > 1 {
+                    break;
+                }
+                EMPTY_RUN_GROWTH
+            } else {
+                self.aim() / count as f32
+            };
+            let density = (generator_config.density * growth).min(max_density);
+            if density == generator_config.density {
+                // the density can't grow any further, so another run creates the same grass
+                break;
+            }
+            generator_config.density = density;
+            let previous = std::mem::replace(&mut grass, generate(generator_config.clone()));
+            if !is_short(&previous) {
+                fallback = Some(previous);
+            }
+        }
+        if is_short(&grass) {
+            if let Some(fallback) = fallback {
+                grass = fallback;
+            }
+        }
+        if grass.instances.len() > target {
+            let mut rand = generator_config.stream_rng(RandomStream::Budget);
+            let mut kept = index::sample(&mut rand, grass.instances.len(), target).into_vec();
+            // keep the order of the generator
+            kept.sort_unstable();
+            grass.instances = kept.into_iter().map(|i| grass.instances[i]).collect();
+        }
+        grass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::combinator::{Subtract, Thinned, Union};
+    use crate::generator::distribution::Distribution;
+    use crate::generator::field::ScalarField;
+    use crate::generator::plane::Plane;
+    use crate::generator::GrassGenerator;
+    use crate::grass::GrassBlade;
+    use bevy::prelude::{Transform, Vec2, Vec3};
+    use std::cell::RefCell;
+
+    fn config(budget: BladeBudget, density: f32) -> StandardGeneratorConfig {
+        StandardGeneratorConfig {
+            density,
+            blade_budget: Some(budget),
+            seed: Some(7),
+            ..Default::default()
+        }
+    }
+
+    fn plane(size: f32, x: f32) -> Plane {
+        Plane::new(Vec2::splat(size)).with_transform(Transform::from_xyz(x, 0., 0.))
+    }
+
+    #[test]
+    fn max_caps_the_blade_count() {
+        let grass = plane(10., 0.).generate_grass(config(BladeBudget::Max(500), 20.));
+        assert_eq!(grass.instances.len(), 500);
+    }
+
+    #[test]
+    fn max_keeps_lower_densities() {
+        let unlimited = plane(10., 0.).generate_grass(StandardGeneratorConfig {
+            density: 2.,
+            seed: Some(7),
+            ..Default::default()
+        });
+        let grass = plane(10., 0.).generate_grass(config(BladeBudget::Max(500), 2.));
+        assert_eq!(grass.instances.len(), unlimited.instances.len());
+    }
+
+    #[test]
+    fn max_with_zero_density_creates_nothing() {
+        let grass = plane(10., 0.).generate_grass(config(BladeBudget::Max(500), 0.));
+        assert!(grass.instances.is_empty());
+    }
+
+    #[test]
+    fn exact_creates_the_count_for_every_distribution() {
+        for distribution in [
+            Distribution::Uniform,
+            Distribution::JitteredGrid,
+            Distribution::PoissonDisk,
+            Distribution::HexGrid,
+        ] {
+            for size in [3., 10., 40.] {
+                let grass = plane(size, 0.).generate_grass(StandardGeneratorConfig {
+                    distribution,
+                    ..config(BladeBudget::Exact(777), 20.)
+                });
+                assert_eq!(grass.instances.len(), 777, "{distribution:?} on {size}");
+            }
+        }
+    }
+
+    #[test]
+    fn exact_ignores_a_zero_density() {
+        let grass = plane(10., 0.).generate_grass(config(BladeBudget::Exact(300), 0.));
+        assert_eq!(grass.instances.len(), 300);
+        let union = Union(plane(10., 0.), plane(10., 5.));
+        let grass = union.generate_grass(config(BladeBudget::Exact(300), 0.));
+        assert_eq!(grass.instances.len(), 300);
+    }
+
+    #[test]
+    fn exact_compensates_filters() {
+        let grass = plane(10., 0.).generate_grass(StandardGeneratorConfig {
+            density_mask: Some(ScalarField::from_fn(
+                |position| {
+                    if position.x < 0. {
+                        0.
+                    } else {
+                        1.
+                    }
+                },
+            )),
+            ..config(BladeBudget::Exact(400), 20.)
+        });
+        assert_eq!(grass.instances.len(), 400);
+        assert!(grass.instances.iter().all(|blade| blade.position.x >= 0.));
+    }
+
+    #[test]
+    fn exact_retries_runs_without_blades() {
+        // the mask removes almost every blade of the first run
+        let grass = plane(10., 0.).generate_grass(StandardGeneratorConfig {
+            density_mask: Some(ScalarField::from_fn(|position| {
+                if position.x > 4. && position.z > 4. {
+                    1.
+                } else {
+                    0.
+                }
+            })),
+            ..config(BladeBudget::Exact(50), 0.)
+        });
+        assert_eq!(grass.instances.len(), 50);
+    }
+
+    #[test]
+    fn exact_gives_up_if_filters_remove_everything() {
+        let grass = plane(10., 0.).generate_grass(StandardGeneratorConfig {
+            density_mask: Some(ScalarField::from_fn(|_| 0.)),
+            ..config(BladeBudget::Exact(10_000), 20.)
+        });
+        assert!(grass.instances.is_empty());
+
+        let densities = RefCell::new(Vec::new());
+        let budget = BladeBudget::Exact(10_000);
+        let grass = budget.fit(config(budget, 20.), Some(100.), |generator_config| {
+            densities.borrow_mut().push(generator_config.density);
+            Grass::default()
+        });
+        assert!(grass.instances.is_empty());
+        let densities = densities.into_inner();
+        assert_eq!(densities.len(), 2);
+        let max_density = budget.aim() / 100. * MAX_DENSITY_GROWTH;
+        assert!(densities.iter().all(|density| *density <= max_density));
+    }
+
+    #[test]
+    fn density_growth_is_capped() {
+        let densities = RefCell::new(Vec::new());
+        let budget = BladeBudget::Exact(10_000);
+        budget.fit(config(budget, 20.), Some(100.), |generator_config| {
+            densities.borrow_mut().push(generator_config.density);
+            // a filter keeping a single blade of every run
+            Grass::new(vec![GrassBlade::new(Vec3::ZERO, 1.)])
+        });
+        let max_density = budget.aim() / 100. * MAX_DENSITY_GROWTH;
+        let densities = densities.into_inner();
+        assert!(densities.iter().all(|density| *density <= max_density));
+        assert!(densities.len() < MAX_RUNS);
+    }
+
+    #[test]
+    fn combinators_apply_the_budget_once() {
+        let union = Union(plane(10., 0.), plane(10., 20.));
+        let grass = union.generate_grass(config(BladeBudget::Max(500), 20.));
+        assert_eq!(grass.instances.len(), 500);
+        let grass = union.generate_grass(config(BladeBudget::Exact(600), 20.));
+        assert_eq!(grass.instances.len(), 600);
+
+        let subtract = Subtract(plane(10., 0.), plane(10., 5.));
+        let grass = subtract.generate_grass(config(BladeBudget::Exact(600), 20.));
+        assert_eq!(grass.instances.len(), 600);
+
+        let thinned = Thinned(plane(10., 0.), 0.3);
+        let grass = thinned.generate_grass(config(BladeBudget::Exact(600), 20.));
+        assert_eq!(grass.instances.len(), 600);
+    }
+
+    #[test]
+    fn zero_budget_creates_nothing() {
+        for budget in [BladeBudget::Exact(0), BladeBudget::Max(0)] {
+            let grass = plane(10., 0.).generate_grass(config(budget, 20.));
+            assert!(grass.instances.is_empty());
+        }
+    }
+
+    #[test]
+    fn budgets_are_reproducible() {
+        let union = Union(plane(10., 0.), plane(10., 5.));
+        let positions = |budget| {
+            union
+                .generate_grass(config(budget, 20.))
+                .instances
+                .iter()
+                .map(|blade| blade.position)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            positions(BladeBudget::Exact(300)),
+            positions(BladeBudget::Exact(300))
+        );
+        assert_eq!(
+            positions(BladeBudget::Max(300)),
+            positions(BladeBudget::Max(300))
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use super::field::ScalarField;
+use bevy::prelude::{Color, Vec3};
+use rand::Rng;
+
+/// How the color tint of the blades varies.
+///
+/// The tint is multiplied with [`RegionConfig::main_color`](crate::RegionConfig::main_color),
+/// so white keeps the color of the region
+#[derive(Debug, Clone, Default)]
+pub enum ColorVariation {
+    /// Every blade has the color of the region
+    #[default]
+    None,
+    /// Each color channel is randomly scaled by 1 ± the given amount
+    Random(f32),
+    /// Each blade picks a random tint out of the palette
+    Palette(Vec<Color>),
+    /// The tint is interpolated between `low` and `high` by the value of the field,
+    /// so neighbouring blades share similar colors
+    Field {
+        field: ScalarField,
+        low: Color,
+        high: Color,
+    },
+}
+
+impl ColorVariation {
+    /// Picks the tint of a blade at `position`, `seed` is only used by noise fields without their own seed
+    pub fn tint(&self, position: Vec3, seed: u64, rand: &mut impl Rng) -> Vec3 {
+        match self {
+            ColorVariation::None => Vec3::ONE,
+            ColorVariation::Random(amount) => {
+                let deviation = Vec3::new(rand.gen(), rand.gen(), rand.gen()) - 0.5;
+                (Vec3::ONE + deviation * 2. * *amount).max(Vec3::ZERO)
+            }
+            ColorVariation::Palette(palette) => {
+                if palette.is_empty() {
+                    return Vec3::ONE;
+                }
+                color_to_vec3(palette[rand.gen_range(0..palette.len())])
+            }
+            ColorVariation::Field { field, low, high } => {
+                let t = field.sample(position, seed).clamp(0., 1.);
+                color_to_vec3(*low).lerp(color_to_vec3(*high), t)
+            }
+        }
+    }
+}
+
+/// The linear color, since the tint is multiplied with the lit color in the shader
+fn color_to_vec3(color: Color) -> Vec3 {
+    let [r, g, b, _] = color.as_linear_rgba_f32();
+    Vec3::new(r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    #[test]
+    fn no_variation_keeps_the_region_color() {
+        let mut rand = SmallRng::seed_from_u64(0);
+        assert_eq!(
+            ColorVariation::None.tint(Vec3::ZERO, 0, &mut rand),
+            Vec3::ONE
+        );
+        let empty = ColorVariation::Palette(Vec::new());
+        assert_eq!(empty.tint(Vec3::ZERO, 0, &mut rand), Vec3::ONE);
+    }
+
+    #[test]
+    fn random_tints_stay_within_the_amount() {
+        let mut rand = SmallRng::seed_from_u64(1);
+        let variation = ColorVariation::Random(0.2);
+        for _ in 0..100 {
+            let tint = variation.tint(Vec3::ZERO, 0, &mut rand);
+            assert!(tint.cmpge(Vec3::splat(0.8)).all() && tint.cmple(Vec3::splat(1.2)).all());
+        }
+        let extreme = ColorVariation::Random(4.);
+        for _ in 0..100 {
+            assert!(extreme
+                .tint(Vec3::ZERO, 0, &mut rand)
+                .cmpge(Vec3::ZERO)
+                .all());
+        }
+    }
+
+    #[test]
+    fn palette_tints_are_picked_from_the_palette() {
+        let mut rand = SmallRng::seed_from_u64(2);
+        let palette = vec![Color::RED, Color::BLUE];
+        let colors: Vec<Vec3> = palette.iter().map(|color| color_to_vec3(*color)).collect();
+        let variation = ColorVariation::Palette(palette);
+        for _ in 0..20 {
+            assert!(colors.contains(&variation.tint(Vec3::ZERO, 0, &mut rand)));
+        }
+    }
+
+    #[test]
+    fn field_tints_interpolate_by_the_field() {
+        let mut rand = SmallRng::seed_from_u64(3);
+        let variation = ColorVariation::Field {
+            field: ScalarField::from_fn(|position| position.x),
+            low: Color::BLACK,
+            high: Color::WHITE,
+        };
+        let half = variation.tint(Vec3::new(0.5, 0., 0.), 0, &mut rand);
+        assert!((half - color_to_vec3(Color::WHITE) * 0.5).length() < 1e-5);
+        // values outside of 0..=1 are clamped
+        assert_eq!(
+            variation.tint(Vec3::new(-2., 0., 0.), 0, &mut rand),
+            Vec3::ZERO
+        );
+        assert_eq!(
+            variation.tint(Vec3::new(3., 0., 0.), 0, &mut rand),
+            Vec3::ONE
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Adapters which combine or modify other generators, to describe complex layouts declaratively.
+//!
+//! Limits of the whole generator call, like the [`BladeBudget`](super::budget::BladeBudget),
+//! are applied to the combined grass, see [`CombinableConfig`].
+use super::{CombinableConfig, GrassArea, GrassGenerator, RandomStream, StandardGeneratorConfig};
+use crate::Grass;
+use bevy::prelude::{Transform, Vec2, Vec3};
+use rand::Rng;
+
+/// Generates the grass of both generators.
+///
+/// Blades of the second generator inside the area of the first one are removed,
+/// so the density doesn't double where both overlap.
+/// The first generator has to be a [`GrassArea`], which all standard generators are.
+pub struct Union<A, B>(pub A, pub B);
+
+impl<C: CombinableConfig, A: GrassGenerator<C> + GrassArea, B: GrassGenerator<C>> GrassGenerator<C>
+    for Union<A, B>
+{
+    fn generate_grass(&self, generator_config: C) -> Grass {
+        generator_config.combine(|generator_config| {
+            let mut grass = self.0.generate_grass(generator_config.clone());
+            let second = self.1.generate_grass(generator_config);
+            grass.instances.extend(
+                second
+                    .instances
+                    .into_iter()
+                    .filter(|blade| !self.0.contains(blade.position)),
+            );
+            grass
+        })
+    }
+}
+
+impl<A: GrassArea, B: GrassArea> GrassArea for Union<A, B> {
+    fn contains(&self, position: Vec3) -> bool {
+        self.0.contains(position) || self.1.contains(position)
+    }
+}
+
+/// Removes all blades of the generator inside of the area of the shape,
+/// for example an [`Exclusion`](super::exclusion::Exclusion) or another generator
+pub struct Subtract<G, S>(pub G, pub S);
+
+impl<C: CombinableConfig, G: GrassGenerator<C>, S: GrassArea> GrassGenerator<C> for Subtract<G, S> {
+    fn generate_grass(&self, generator_config: C) -> Grass {
+        generator_config.combine(|generator_config| {
+            let mut grass = self.0.generate_grass(generator_config);
+            grass
+                .instances
+                .retain(|blade| !self.1.contains(blade.position));
+            grass
+        })
+    }
+}
+
+impl<G: GrassArea, S: GrassArea> GrassArea for Subtract<G, S> {
+    fn contains(&self, position: Vec3) -> bool {
+        self.0.contains(position) && !self.1.contains(position)
+    }
+}
+
+/// Keeps only the blades of the generator inside of the area of the shape
+pub struct Intersect<G, S>(pub G, pub S);
+
+impl<C: CombinableConfig, G: GrassGenerator<C>, S: GrassArea> GrassGenerator<C>
+    for Intersect<G, S>
+{
+    fn generate_grass(&self, generator_config: C) -> Grass {
+        generator_config.combine(|generator_config| {
+            let mut grass = self.0.generate_grass(generator_config);
+            grass
+                .instances
+                .retain(|blade| self.1.contains(blade.position));
+            grass
+        })
+    }
+}
+
+impl<G: GrassArea, S: GrassArea> GrassArea for Intersect<G, S> {
+    fn contains(&self, position: Vec3) -> bool {
+        self.0.contains(position) && self.1.contains(position)
+    }
+}
+
+/// Moves the grass of the generator with a [`Transform`].
+///
+/// The positions and facings of the blades are transformed, their sizes stay the same
+pub struct Transformed<G>(pub G, pub Transform);
+
+impl<C, G: GrassGenerator<C>> GrassGenerator<C> for Transformed<G> {
+    fn generate_grass(&self, generator_config: C) -> Grass {
+        let mut grass = self.0.generate_grass(generator_config);
+        let matrix = self.1.compute_affine();
+        let rotation = self.1.rotation;
+        for blade in &mut grass.instances {
+            blade.position = matrix.transform_point3(blade.position);
+            // only the part around the y axis changes the facing,
+            // the blades turn from x towards z, opposite to `Quat::from_rotation_y`
+            let facing = rotation * Vec3::X;
+            blade.rotation += facing.z.atan2(facing.x);
+            let lean = rotation * Vec3::new(blade.lean.x, 0., blade.lean.y);
+            blade.lean = Vec2::new(lean.x, lean.z);
+            blade.up = (rotation * blade.up).normalize();
+        }
+        grass
+    }
+}
+
+impl<G: GrassArea> GrassArea for Transformed<G> {
+    fn contains(&self, position: Vec3) -> bool {
+        let inverse = self.1.compute_affine().inverse();
+        self.0.contains(inverse.transform_point3(position))
+    }
+}
+
+/// Randomly removes blades of the generator, keeping about `factor` (between 0 and 1) of them.
+///
+/// The removal is reproducible from [`StandardGeneratorConfig::seed`]
+pub struct Thinned<G>(pub G, pub f32);
+
+impl<G: GrassGenerator<StandardGeneratorConfig>> GrassGenerator<StandardGeneratorConfig>
+    for Thinned<G>
+{
+    fn generate_grass(&self, generator_config: StandardGeneratorConfig) -> Grass {
+        generator_config.combine(|generator_config| {
+            let mut rand = generator_config.stream_rng(RandomStream::Thinning);
+            let mut grass = self.0.generate_grass(generator_config);
+            grass.instances.retain(|_| rand.gen::<f32>() < self.1);
+            grass
+        })
+    }
+}
+
+impl<G: GrassArea> GrassArea for Thinned<G> {
+    fn contains(&self, position: Vec3) -> bool {
+        self.0.contains(position)
+    }
+}
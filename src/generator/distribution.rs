@@ -0,0 +1,285 @@
+use bevy::prelude::{IVec2, Vec2};
+use rand::{rngs::SmallRng, Rng};
+use std::f32::consts::TAU;
+
+/// How the positions of the grass blades are spread over the area of a generator.
+///
+/// Every mode is reproducible from [`StandardGeneratorConfig::seed`](super::StandardGeneratorConfig::seed)
+/// and produces about [`StandardGeneratorConfig::density`](super::StandardGeneratorConfig::density) blades per area.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Distribution {
+    /// Independent uniformly random positions.
+    ///
+    /// This is the cheapest mode, but leaves visible clumps and bald spots at low densities
+    #[default]
+    Uniform,
+    /// One random position inside each cell of a square grid
+    JitteredGrid,
+    /// Random positions keeping a minimum distance to each other (blue noise).
+    ///
+    /// This gives the most natural even coverage, but is also the most expensive mode
+    PoissonDisk,
+    /// Positions on a hexagonal grid with a random offset
+    HexGrid,
+}
+
+impl Distribution {
+    /// Samples positions inside the rectangle from the origin to `size` and maps each of them with `map`.
+    ///
+    /// [`Distribution::Uniform`] calls `map` right after drawing each position, so the random numbers
+    /// of each position and its blade stay interleaved like in the previous plane generator,
+    /// see [`Plane::legacy`](super::plane::Plane::legacy).
+    pub(crate) fn sample_map<T>(
+        &self,
+        size: Vec2,
+        density: f32,
+        rand: &mut SmallRng,
+        mut map: impl FnMut(Vec2, &mut SmallRng) -> Option<T>,
+    ) -> Vec<T> {
+        match self {
+            Distribution::Uniform => {
+                let size = size.abs();
+                let area = size.x * size.y;
+                if area <= 0. || density <= 0. {
+                    return Vec::new();
+                }
+                let count = (area * density) as usize;
+                (0..count)
+                    .filter_map(|_| {
+                        let point = Vec2::new(rand.gen(), rand.gen()) * size;
+                        map(point, rand)
+                    })
+                    .collect()
+            }
+            distribution => distribution
+                .sample(size, density, rand)
+                .into_iter()
+                .filter_map(|point| map(point, rand))
+                .collect(),
+        }
+    }
+    /// Samples positions inside the rectangle from the origin to `size`
+    pub(crate) fn sample(&self, size: Vec2, density: f32, rand: &mut SmallRng) -> Vec<Vec2> {
+        let size = size.abs();
+        let area = size.x * size.y;
+        if area <= 0. || density <= 0. {
+            return Vec::new();
+        }
+        match self {
+            Distribution::Uniform => {
+                let count = (area * density) as usize;
+                (0..count)
+                    .map(|_| Vec2::new(rand.gen(), rand.gen()) * size)
+                    .collect()
+            }
+            Distribution::JitteredGrid => {
+                let cell = 1. / density.sqrt();
+                let offset = Vec2::new(rand.gen(), rand.gen()) * cell;
+                let cells = (size / cell).ceil().as_ivec2() + 1;
+                let mut points = Vec::new();
+                for x in 0..cells.x {
+                    for z in 0..cells.y {
+                        let jitter = Vec2::new(rand.gen(), rand.gen());
+                        let point = (Vec2::new(x as f32, z as f32) + jitter) * cell - offset;
+                        if inside(point, size) {
+                            points.push(point);
+                        }
+                    }
+                }
+                points
+            }
+            Distribution::HexGrid => {
+                // each point covers a hexagon with an area of sqrt(3)/2 * spacing^2
+                let spacing = (2. / (3f32.sqrt() * density)).sqrt();
+                let row_spacing = spacing * 3f32.sqrt() / 2.;
+                let offset =
+                    Vec2::new(rand.gen::<f32>() * spacing, rand.gen::<f32>() * row_spacing);
+                let columns = (size.x / spacing).ceil() as i32 + 2;
+                let rows = (size.y / row_spacing).ceil() as i32 + 2;
+                let mut points = Vec::new();
+                for row in 0..rows {
+                    let shift = if row % 2 == 0 { 0. } else { spacing / 2. };
+                    for column in 0..columns {
+                        let point =
+                            Vec2::new(column as f32 * spacing + shift, row as f32 * row_spacing)
+                                - offset;
+                        if inside(point, size) {
+                            points.push(point);
+                        }
+                    }
+                }
+                points
+            }
+            Distribution::PoissonDisk => poisson_disk(size, density, rand),
+        }
+    }
+}
+
+fn inside(point: Vec2, size: Vec2) -> bool {
+    point.x >= 0. && point.y >= 0. && point.x < size.x && point.y < size.y
+}
+
+/// Number of candidates tried around an active point before it is retired
+const POISSON_ATTEMPTS: usize = 30;
+
+/// Bridson's poisson disk sampling.
+///
+/// A maximal poisson disk set with radius r has about 0.7 / r^2 points per area,
+/// which is used to derive the radius from the density.
+fn poisson_disk(size: Vec2, density: f32, rand: &mut SmallRng) -> Vec<Vec2> {
+    let radius = (0.7 / density).sqrt();
+    let cell = radius / 2f32.sqrt();
+    let grid_size = (size / cell).ceil().as_ivec2().max(IVec2::ONE);
+    let mut grid: Vec<Option<usize>> = vec![None; (grid_size.x * grid_size.y) as usize];
+    let grid_index = |point: Vec2| {
+        let cell = (point / cell).as_ivec2().min(grid_size - 1);
+        (cell, (cell.y * grid_size.x + cell.x) as usize)
+    };
+
+    let mut points = Vec::new();
+    let mut active = Vec::new();
+    let first = Vec2::new(rand.gen(), rand.gen()) * size;
+    grid[grid_index(first).1] = Some(0);
+    points.push(first);
+    active.push(0);
+
+    while !active.is_empty() {
+        let active_index = rand.gen_range(0..active.len());
+        let center = points[active[active_index]];
+        let candidate = (0..POISSON_ATTEMPTS)
+            .map(|_| {
+                let angle = rand.gen::<f32>() * TAU;
+                let distance = radius * (1. + rand.gen::<f32>());
+                center + Vec2::new(angle.cos(), angle.sin()) * distance
+            })
+            .find(|&candidate| {
+                if !inside(candidate, size) {
+                    return false;
+                }
+                let (cell, _) = grid_index(candidate);
+                let min = (cell - 2).max(IVec2::ZERO);
+                let max = (cell + 2).min(grid_size - 1);
+                (min.y..=max.y).all(|y| {
+                    (min.x..=max.x).all(|x| match grid[(y * grid_size.x + x) as usize] {
+                        Some(other) => points[other].distance_squared(candidate) >= radius * radius,
+                        None => true,
+                    })
+                })
+            });
+        match candidate {
+            Some(candidate) => {
+                grid[grid_index(candidate).1] = Some(points.len());
+                active.push(points.len());
+                points.push(candidate);
+            }
+            None => {
+                active.swap_remove(active_index);
+            }
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    const MODES: [Distribution; 4] = [
+        Distribution::Uniform,
+        Distribution::JitteredGrid,
+        Distribution::PoissonDisk,
+        Distribution::HexGrid,
+    ];
+
+    #[test]
+    fn samples_stay_inside_the_area() {
+        let size = Vec2::new(8., 5.);
+        for mode in MODES {
+            let points = mode.sample(size, 4., &mut SmallRng::seed_from_u64(1));
+            assert!(!points.is_empty(), "{mode:?}");
+            assert!(points.iter().all(|&point| inside(point, size)), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn sample_counts_follow_the_density() {
+        let size = Vec2::new(20., 20.);
+        let density = 5.;
+        let expected = size.x * size.y * density;
+        for mode in MODES {
+            let count = mode
+                .sample(size, density, &mut SmallRng::seed_from_u64(2))
+                .len() as f32;
+            assert!(
+                (count - expected).abs() < expected * 0.2,
+                "{mode:?} created {count} instead of about {expected} points"
+            );
+        }
+    }
+
+    #[test]
+    fn empty_areas_and_densities_create_nothing() {
+        for mode in MODES {
+            let mut rand = SmallRng::seed_from_u64(3);
+            assert!(mode.sample(Vec2::new(0., 4.), 2., &mut rand).is_empty());
+            assert!(mode.sample(Vec2::new(4., 4.), 0., &mut rand).is_empty());
+            assert!(mode.sample(Vec2::new(4., 4.), -1., &mut rand).is_empty());
+        }
+    }
+
+    #[test]
+    fn samples_are_reproducible() {
+        for mode in MODES {
+            let sample =
+                |seed| mode.sample(Vec2::new(6., 6.), 3., &mut SmallRng::seed_from_u64(seed));
+            assert_eq!(sample(4), sample(4), "{mode:?}");
+            assert_ne!(sample(4), sample(5), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn poisson_disk_keeps_the_minimum_distance() {
+        let density = 4.;
+        let radius = (0.7f32 / density).sqrt();
+        let points = poisson_disk(Vec2::new(6., 6.), density, &mut SmallRng::seed_from_u64(6));
+        for (i, a) in points.iter().enumerate() {
+            for b in &points[i + 1..] {
+                assert!(a.distance(*b) >= radius * 0.999);
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_mapping_interleaves_the_random_numbers() {
+        let size = Vec2::new(3., 2.);
+        let mapped = Distribution::Uniform.sample_map(
+            size,
+            2.,
+            &mut SmallRng::seed_from_u64(7),
+            |point, rand| Some((point, rand.gen::<f32>())),
+        );
+        let mut rand = SmallRng::seed_from_u64(7);
+        let expected: Vec<(Vec2, f32)> = (0..12)
+            .map(|_| {
+                let (x, z): (f32, f32) = rand.gen();
+                (Vec2::new(x, z) * size, rand.gen())
+            })
+            .collect();
+        assert_eq!(mapped, expected);
+    }
+
+    #[test]
+    fn mapping_skips_rejected_points() {
+        for mode in MODES {
+            let mapped = mode.sample_map(
+                Vec2::new(4., 4.),
+                4.,
+                &mut SmallRng::seed_from_u64(8),
+                |point, _| (point.x < 2.).then_some(point),
+            );
+            assert!(!mapped.is_empty(), "{mode:?}");
+            assert!(mapped.iter().all(|point| point.x < 2.), "{mode:?}");
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use super::polygon::Polygon;
+use super::GrassArea;
+use bevy::math::Vec3A;
+use bevy::prelude::{GlobalTransform, Vec2, Vec3};
+use bevy::render::primitives::Aabb;
+
+/// An area where no grass blades are placed.
+///
+/// The shapes are in the same space as the positions of the generated grass.
+/// Blades are tested with their root position as soon as it is known,
+/// before their surface normal is measured or any random number is drawn for them.
+#[derive(Debug, Clone)]
+pub enum Exclusion {
+    /// A box, removing every blade with its root inside
+    Aabb(Aabb),
+    /// A rectangle on the XZ plane
+    Rect { min: Vec2, max: Vec2 },
+    /// A circle on the XZ plane
+    Circle { center: Vec2, radius: f32 },
+    /// A polygon on the XZ plane, blades inside of its holes are kept
+    Polygon(Polygon),
+}
+
+impl Exclusion {
+    /// Creates an exclusion covering an existing entity.
+    ///
+    /// The [`Aabb`] of the entity is transformed into world space,
+    /// so this matches grass spawned at the world origin.
+    pub fn from_entity_aabb(aabb: &Aabb, transform: &GlobalTransform) -> Self {
+        let affine = transform.affine();
+        let (min, max) = (aabb.min(), aabb.max());
+        let mut world_min = Vec3A::splat(f32::MAX);
+        let mut world_max = Vec3A::splat(f32::MIN);
+        for corner in 0..8 {
+            let local = Vec3A::new(
+                if corner & 1 == 0 { min.x } else { max.x },
+                if corner & 2 == 0 { min.y } else { max.y },
+                if corner & 4 == 0 { min.z } else { max.z },
+            );
+            let world = affine.transform_point3a(local);
+            world_min = world_min.min(world);
+            world_max = world_max.max(world);
+        }
+        Exclusion::Aabb(Aabb::from_min_max(world_min.into(), world_max.into()))
+    }
+}
+
+impl GrassArea for Exclusion {
+    /// Checks if a blade at `position` is excluded
+    fn contains(&self, position: Vec3) -> bool {
+        let point = Vec2::new(position.x, position.z);
+        match self {
+            Exclusion::Aabb(aabb) => {
+                let position = Vec3A::from(position);
+                position.cmpge(aabb.min()).all() && position.cmple(aabb.max()).all()
+            }
+            Exclusion::Rect { min, max } => point.cmpge(*min).all() && point.cmple(*max).all(),
+            Exclusion::Circle { center, radius } => {
+                point.distance_squared(*center) <= radius * radius
+            }
+            Exclusion::Polygon(polygon) => polygon.contains(point),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::plane::Plane;
+    use crate::generator::{GrassGenerator, StandardGeneratorConfig};
+    use bevy::prelude::{Quat, Transform};
+    use std::f32::consts::{FRAC_PI_4, SQRT_2};
+
+    #[test]
+    fn shapes_contain_their_area() {
+        let rect = Exclusion::Rect {
+            min: Vec2::new(-1., 2.),
+            max: Vec2::new(3., 4.),
+        };
+        assert!(rect.contains(Vec3::new(0., 100., 3.)));
+        assert!(rect.contains(Vec3::new(3., 0., 4.)));
+        assert!(!rect.contains(Vec3::new(0., 0., 1.)));
+
+        let circle = Exclusion::Circle {
+            center: Vec2::new(1., 1.),
+            radius: 2.,
+        };
+        assert!(circle.contains(Vec3::new(2., -5., 2.)));
+        assert!(!circle.contains(Vec3::new(2.5, 0., 2.5)));
+
+        let aabb = Exclusion::Aabb(Aabb::from_min_max(Vec3::ZERO, Vec3::ONE));
+        assert!(aabb.contains(Vec3::splat(0.5)));
+        // the box also limits the height
+        assert!(!aabb.contains(Vec3::new(0.5, 2., 0.5)));
+
+        let square = |min: f32, max: f32| {
+            vec![
+                Vec2::new(min, min),
+                Vec2::new(max, min),
+                Vec2::new(max, max),
+                Vec2::new(min, max),
+            ]
+        };
+        let polygon = Exclusion::Polygon(Polygon::new(square(0., 4.)).with_hole(square(1., 2.)));
+        assert!(polygon.contains(Vec3::new(3., 0., 3.)));
+        assert!(!polygon.contains(Vec3::new(1.5, 0., 1.5)));
+    }
+
+    #[test]
+    fn entity_aabb_covers_all_transformed_corners() {
+        let aabb = Aabb::from_min_max(Vec3::new(-1., 0., -1.), Vec3::new(1., 2., 1.));
+        let transform = GlobalTransform::from(
+            Transform::from_xyz(10., 1., 0.)
+                .with_rotation(Quat::from_rotation_y(FRAC_PI_4))
+                .with_scale(Vec3::splat(2.)),
+        );
+        let exclusion = Exclusion::from_entity_aabb(&aabb, &transform);
+        let world = match exclusion {
+            Exclusion::Aabb(world) => world,
+            other => panic!("expected a box, got {other:?}"),
+        };
+        let reach = 2. * SQRT_2;
+        let expected_min = Vec3::new(10. - reach, 1., -reach);
+        let expected_max = Vec3::new(10. + reach, 5., reach);
+        assert!(Vec3::from(world.min()).abs_diff_eq(expected_min, 1e-4));
+        assert!(Vec3::from(world.max()).abs_diff_eq(expected_max, 1e-4));
+    }
+
+    #[test]
+    fn generators_place_no_blades_inside_exclusions() {
+        let exclusion = Exclusion::Circle {
+            center: Vec2::ZERO,
+            radius: 3.,
+        };
+        let grass = Plane::new(Vec2::splat(10.)).generate_grass(StandardGeneratorConfig {
+            seed: Some(1),
+            exclusions: vec![exclusion.clone()],
+            ..Default::default()
+        });
+        assert!(!grass.instances.is_empty());
+        assert!(grass
+            .instances
+            .iter()
+            .all(|blade| !exclusion.contains(blade.position)));
+    }
+}
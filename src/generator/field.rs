@@ -0,0 +1,95 @@
+use super::noise::NoiseField;
+use super::texture;
+use bevy::prelude::{Image, Vec2, Vec3};
+use std::fmt;
+use std::sync::Arc;
+
+/// A value which varies over the area of a generator.
+///
+/// Fields are used to modulate the placement and look of the blades,
+/// for example to paint paths, clearings or patches of tall grass.
+/// They are sampled in the same space as the positions of the generated grass.
+#[derive(Clone)]
+pub enum ScalarField {
+    /// A grayscale texture laid out on the XZ plane
+    Texture(TextureField),
+    /// Procedural noise, seeded from the generator config unless it has its own seed
+    Noise(NoiseField),
+    /// A custom function of the blade position
+    Function(Arc<dyn Fn(Vec3) -> f32 + Send + Sync>),
+}
+
+impl fmt::Debug for ScalarField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScalarField::Texture(texture) => f.debug_tuple("Texture").field(texture).finish(),
+            ScalarField::Noise(noise) => f.debug_tuple("Noise").field(noise).finish(),
+            ScalarField::Function(_) => write!(f, "Function"),
+        }
+    }
+}
+
+impl From<TextureField> for ScalarField {
+    fn from(texture: TextureField) -> Self {
+        ScalarField::Texture(texture)
+    }
+}
+
+impl From<NoiseField> for ScalarField {
+    fn from(noise: NoiseField) -> Self {
+        ScalarField::Noise(noise)
+    }
+}
+
+impl ScalarField {
+    /// Creates a field from a function of the blade position
+    pub fn from_fn(function: impl Fn(Vec3) -> f32 + Send + Sync + 'static) -> Self {
+        ScalarField::Function(Arc::new(function))
+    }
+    /// Samples the field at `position`, `seed` is only used by noise fields without their own seed
+    pub fn sample(&self, position: Vec3, seed: u64) -> f32 {
+        match self {
+            ScalarField::Texture(texture) => texture.sample(position),
+            ScalarField::Noise(noise) => noise.sample(position, seed),
+            ScalarField::Function(function) => function(position),
+        }
+    }
+    /// Gives a noise field without a seed of its own the fixed `seed`
+    pub(crate) fn fix_seed(&mut self, seed: u64) {
+        if let ScalarField::Noise(noise) = self {
+            noise.seed.get_or_insert(seed);
+        }
+    }
+}
+
+/// A grayscale texture laid out on the XZ plane.
+///
+/// The texture covers `size` on the X and Z axis around `center`,
+/// outside of this area the nearest edge texel is used.
+#[derive(Debug, Clone)]
+pub struct TextureField {
+    /// The image.
+    ///
+    /// Only the first channel is read, see [`Heightmap::image`](super::heightmap::Heightmap::image) for supported formats
+    pub image: Image,
+    /// The extents of the texture on the X and Z axis
+    pub size: Vec2,
+    /// The center of the texture on the X and Z axis
+    pub center: Vec2,
+}
+
+impl TextureField {
+    /// Creates a texture field covering `size` around the origin
+    pub fn new(image: Image, size: Vec2) -> Self {
+        TextureField {
+            image,
+            size,
+            center: Vec2::ZERO,
+        }
+    }
+    /// Samples the texture at `position` with bilinear filtering, returning a value between 0 and 1
+    pub fn sample(&self, position: Vec3) -> f32 {
+        let uv = (Vec2::new(position.x, position.z) - self.center) / self.size + 0.5;
+        texture::sample_bilinear(&self.image, uv)
+    }
+}
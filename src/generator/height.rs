@@ -0,0 +1,71 @@
+use rand::Rng;
+use std::f32::consts::TAU;
+use std::fmt;
+use std::sync::Arc;
+
+/// The statistical distribution of the blade heights
+#[derive(Clone)]
+pub enum HeightDistribution {
+    /// Every height between `min` and `max` is equally likely
+    Uniform { min: f32, max: f32 },
+    /// Heights are normally distributed around `mean`
+    Normal { mean: f32, std_dev: f32 },
+    /// The logarithm of the heights is normally distributed with `mu` and `sigma`.
+    ///
+    /// This gives mostly short blades with a few tall outliers
+    LogNormal { mu: f32, sigma: f32 },
+    /// A custom curve, mapping a uniformly random value between 0 and 1 to a height.
+    ///
+    /// This is the inverse of the cumulative distribution function of the heights
+    Curve(Arc<dyn Fn(f32) -> f32 + Send + Sync>),
+}
+
+impl fmt::Debug for HeightDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeightDistribution::Uniform { min, max } => f
+                .debug_struct("Uniform")
+                .field("min", min)
+                .field("max", max)
+                .finish(),
+            HeightDistribution::Normal { mean, std_dev } => f
+                .debug_struct("Normal")
+                .field("mean", mean)
+                .field("std_dev", std_dev)
+                .finish(),
+            HeightDistribution::LogNormal { mu, sigma } => f
+                .debug_struct("LogNormal")
+                .field("mu", mu)
+                .field("sigma", sigma)
+                .finish(),
+            HeightDistribution::Curve(_) => write!(f, "Curve"),
+        }
+    }
+}
+
+impl HeightDistribution {
+    /// Creates a distribution from a custom curve, see [`HeightDistribution::Curve`]
+    pub fn from_curve(curve: impl Fn(f32) -> f32 + Send + Sync + 'static) -> Self {
+        HeightDistribution::Curve(Arc::new(curve))
+    }
+    /// Draws a random height, which is never negative
+    pub fn sample(&self, rand: &mut impl Rng) -> f32 {
+        let height = match self {
+            HeightDistribution::Uniform { min, max } => min + rand.gen::<f32>() * (max - min),
+            HeightDistribution::Normal { mean, std_dev } => mean + std_dev * standard_normal(rand),
+            HeightDistribution::LogNormal { mu, sigma } => {
+                (mu + sigma * standard_normal(rand)).exp()
+            }
+            HeightDistribution::Curve(curve) => curve(rand.gen()),
+        };
+        height.max(0.)
+    }
+}
+
+/// Draws a normally distributed value with the Box-Muller transform
+fn standard_normal(rand: &mut impl Rng) -> f32 {
+    // shift into (0, 1] to avoid the logarithm of 0
+    let u1 = 1. - rand.gen::<f32>();
+    let u2 = rand.gen::<f32>();
+    (-2. * u1.ln()).sqrt() * (TAU * u2).cos()
+}
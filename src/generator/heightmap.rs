@@ -0,0 +1,82 @@
+use super::{texture, GrassArea, GrassGenerator, RandomStream, StandardGeneratorConfig};
+use crate::Grass;
+use bevy::prelude::{Image, Vec2, Vec3};
+
+/// Places grass blades on the surface described by a grayscale heightmap.
+///
+/// The heightmap covers `size` on the X and Z axis, centered around the local origin.
+/// The first row of the image lies at `-size.y / 2` on the Z axis.
+/// The red channel of every texel is read as a value between 0 and 1 and multiplied with `height_scale`.
+#[derive(Debug, Clone)]
+pub struct Heightmap {
+    /// The heightmap image.
+    ///
+    /// Supported are 8 bit, 16 bit and 32 bit float textures with up to four channels,
+    /// of which only the first channel is read
+    pub image: Image,
+    /// The world extents of the heightmap on the X and Z axis
+    pub size: Vec2,
+    /// The height of a white texel
+    pub height_scale: f32,
+}
+
+impl Heightmap {
+    pub fn new(image: Image, size: Vec2, height_scale: f32) -> Self {
+        Heightmap {
+            image,
+            size,
+            height_scale,
+        }
+    }
+    /// Returns true if the format of the heightmap image can be read by the generator
+    pub fn is_supported(&self) -> bool {
+        texture::is_supported(self.image.texture_descriptor.format)
+    }
+    /// Samples the height at the local `x` and `z` coordinates with bilinear filtering
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        let uv = Vec2::new(x, z) / self.size + 0.5;
+        texture::sample_bilinear(&self.image, uv) * self.height_scale
+    }
+    /// The surface normal at the local `x` and `z` coordinates, using the heights of the neighbouring texels
+    pub fn normal_at(&self, x: f32, z: f32) -> Vec3 {
+        let step = self.size / self.image.size().max(Vec2::ONE);
+        let dx = self.height_at(x + step.x, z) - self.height_at(x - step.x, z);
+        let dz = self.height_at(x, z + step.y) - self.height_at(x, z - step.y);
+        Vec3::new(-dx / (2. * step.x), 1., -dz / (2. * step.y)).normalize()
+    }
+}
+
+impl GrassArea for Heightmap {
+    fn contains(&self, position: Vec3) -> bool {
+        let half_size = self.size.abs() / 2.;
+        position.x.abs() <= half_size.x && position.z.abs() <= half_size.y
+    }
+}
+
+impl GrassGenerator<StandardGeneratorConfig> for Heightmap {
+    fn generate_grass(&self, generator_config: StandardGeneratorConfig) -> Grass {
+        let size = self.size.abs();
+        generator_config.within_budget(Some(size.x * size.y), |generator_config| {
+            let mut rand = generator_config.rng();
+            let mut look = generator_config.stream_rng(RandomStream::Look);
+            let blades = generator_config.distribution.sample_map(
+                size,
+                generator_config.density,
+                &mut rand,
+                |point, rand| {
+                    let (x, z) = (point.x - size.x / 2., point.y - size.y / 2.);
+                    let position = Vec3::new(x, self.height_at(x, z), z);
+                    if generator_config.excludes(position) {
+                        return None;
+                    }
+                    let normal = self.normal_at(x, z);
+                    if !generator_config.accepts(position, normal, rand) {
+                        return None;
+                    }
+                    Some(generator_config.blade(position, normal, rand, &mut look))
+                },
+            );
+            Grass { instances: blades }
+        })
+    }
+}
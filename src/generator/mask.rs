@@ -0,0 +1,35 @@
+use super::field::{ScalarField, TextureField};
+use bevy::prelude::{Image, Vec2, Vec3};
+
+/// A grayscale texture which scales the probability of a grass blade being placed.
+///
+/// White texels keep every blade, black texels remove all of them,
+/// which makes it possible to paint paths, clearings or patchy lawns.
+/// The mask is laid out like its [`TextureField`], in the same space as the positions of the generated grass.
+///
+/// It is used as [`StandardGeneratorConfig::density_mask`](super::StandardGeneratorConfig::density_mask) by converting it into a [`ScalarField`].
+#[derive(Debug, Clone)]
+pub struct DensityMask(pub TextureField);
+
+impl DensityMask {
+    /// Creates a mask covering `size` around the origin
+    pub fn new(image: Image, size: Vec2) -> Self {
+        DensityMask(TextureField::new(image, size))
+    }
+    /// The probability between 0 and 1 for a blade at `position` to be placed
+    pub fn density_at(&self, position: Vec3) -> f32 {
+        self.0.sample(position).clamp(0., 1.)
+    }
+}
+
+impl From<TextureField> for DensityMask {
+    fn from(texture: TextureField) -> Self {
+        DensityMask(texture)
+    }
+}
+
+impl From<DensityMask> for ScalarField {
+    fn from(mask: DensityMask) -> Self {
+        ScalarField::Texture(mask.0)
+    }
+}
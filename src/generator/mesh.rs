@@ -0,0 +1,269 @@
+use super::{Distribution, GrassArea, GrassGenerator, RandomStream, StandardGeneratorConfig};
+use crate::Grass;
+use bevy::prelude::{Mesh, Vec2, Vec3};
+use bevy::render::mesh::PrimitiveTopology;
+use rand::{rngs::SmallRng, Rng};
+
+/// Scatters grass blades over the surface of a [`Mesh`].
+///
+/// The blades are distributed over the triangles weighted by their area,
+/// so the density stays the same on uneven terrain.
+/// Apart from [`Distribution::Uniform`], the distribution is applied to each triangle separately.
+/// The positions are in the local space of the mesh.
+#[derive(Debug, Clone)]
+pub struct MeshSurface {
+    triangles: Vec<[Vec3; 3]>,
+    /// Running sum of the triangle areas, used to pick a triangle by area
+    cumulative_areas: Vec<f32>,
+}
+
+impl MeshSurface {
+    /// Collects the triangles of `mesh`.
+    ///
+    /// Returns [None] if the mesh is not a [`PrimitiveTopology::TriangleList`]
+    /// or has no `Float32x3` positions.
+    /// Meshes without indices are read as consecutive vertex triples.
+    pub fn from_mesh(mesh: &Mesh) -> Option<Self> {
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return None;
+        }
+        let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION)?.as_float3()?;
+        let indices: Vec<usize> = match mesh.indices() {
+            Some(indices) => indices.iter().collect(),
+            None => (0..positions.len()).collect(),
+        };
+        let triangles = indices
+            .chunks_exact(3)
+            .filter_map(|triangle| {
+                let a = positions.get(triangle[0])?;
+                let b = positions.get(triangle[1])?;
+                let c = positions.get(triangle[2])?;
+                Some([Vec3::from(*a), Vec3::from(*b), Vec3::from(*c)])
+            })
+            .collect();
+        Some(Self::from_triangles(triangles))
+    }
+
+    /// Creates a surface from a list of triangles
+    pub fn from_triangles(triangles: Vec<[Vec3; 3]>) -> Self {
+        let mut area = 0.;
+        let cumulative_areas = triangles
+            .iter()
+            .map(|[a, b, c]| {
+                area += (*b - *a).cross(*c - *a).length() * 0.5;
+                area
+            })
+            .collect();
+        MeshSurface {
+            triangles,
+            cumulative_areas,
+        }
+    }
+
+    /// The total area of all triangles
+    pub fn area(&self) -> f32 {
+        self.cumulative_areas.last().copied().unwrap_or(0.)
+    }
+}
+
+impl MeshSurface {
+    /// Picks random positions weighted by the triangle areas and maps them with the normal of their triangle.
+    ///
+    /// `map` is called right after each position is drawn, like [`Distribution::sample_map`] does for uniform positions
+    fn sample_uniform<T>(
+        &self,
+        density: f32,
+        rand: &mut SmallRng,
+        mut map: impl FnMut((Vec3, Vec3), &mut SmallRng) -> Option<T>,
+    ) -> Vec<T> {
+        let area = self.area();
+        let blades_count = (area * density) as usize;
+        (0..blades_count)
+            .filter_map(|_| {
+                // pick a triangle weighted by its area
+                let target = rand.gen::<f32>() * area;
+                let index = self
+                    .cumulative_areas
+                    .partition_point(|&cumulative| cumulative <= target)
+                    .min(self.triangles.len() - 1);
+                let [a, b, c] = self.triangles[index];
+                // uniform point inside the triangle
+                let (mut u, mut v): (f32, f32) = rand.gen();
+                if u + v > 1. {
+                    u = 1. - u;
+                    v = 1. - v;
+                }
+                let position = a + (b - a) * u + (c - a) * v;
+                map((position, triangle_normal(a, b, c)), rand)
+            })
+            .collect()
+    }
+    /// Samples every triangle on its own, by sampling the bounding rectangle of the triangle in its own plane.
+    ///
+    /// Note that the distribution is not continued over triangle edges.
+    fn sample_triangles(
+        &self,
+        distribution: Distribution,
+        density: f32,
+        rand: &mut SmallRng,
+    ) -> Vec<(Vec3, Vec3)> {
+        let mut positions = Vec::new();
+        for &[a, b, c] in &self.triangles {
+            let normal = (b - a).cross(c - a);
+            if normal.length_squared() <= f32::EPSILON {
+                continue;
+            }
+            // orthonormal frame in the plane of the triangle with `a` as origin
+            let normal = normal.normalize();
+            let tangent = (b - a).normalize();
+            let bitangent = normal.cross(tangent);
+            let b2 = Vec2::new((b - a).dot(tangent), 0.);
+            let c2 = Vec2::new((c - a).dot(tangent), (c - a).dot(bitangent));
+            let min = b2.min(c2).min(Vec2::ZERO);
+            let max = b2.max(c2).max(Vec2::ZERO);
+            for point in distribution.sample(max - min, density, rand) {
+                let point = point + min;
+                if inside_triangle(point, b2, c2) {
+                    positions.push((a + tangent * point.x + bitangent * point.y, normal));
+                }
+            }
+        }
+        positions
+    }
+}
+
+/// The normal of the front face, using the counter-clockwise winding order of bevy
+fn triangle_normal(a: Vec3, b: Vec3, c: Vec3) -> Vec3 {
+    (b - a).cross(c - a).normalize_or_zero()
+}
+
+/// Checks if `point` is inside the triangle spanned by the origin, `b` and `c`
+fn inside_triangle(point: Vec2, b: Vec2, c: Vec2) -> bool {
+    let det = b.perp_dot(c);
+    let u = point.perp_dot(c) / det;
+    let v = b.perp_dot(point) / det;
+    u >= 0. && v >= 0. && u + v <= 1.
+}
+
+impl GrassArea for MeshSurface {
+    /// Checks if the position lies above or below one of the triangles.
+    ///
+    /// Every triangle is tested, so large meshes are slow to use as the first generator of a [`Union`](super::combinator::Union)
+    fn contains(&self, position: Vec3) -> bool {
+        let point = Vec2::new(position.x, position.z);
+        self.triangles.iter().any(|&[a, b, c]| {
+            let a = Vec2::new(a.x, a.z);
+            let (b, c) = (Vec2::new(b.x, b.z) - a, Vec2::new(c.x, c.z) - a);
+            // vertical triangles don't cover any area on the XZ plane
+            b.perp_dot(c).abs() > f32::EPSILON && inside_triangle(point - a, b, c)
+        })
+    }
+}
+
+impl GrassGenerator<StandardGeneratorConfig> for MeshSurface {
+    fn generate_grass(&self, generator_config: StandardGeneratorConfig) -> Grass {
+        generator_config.within_budget(Some(self.area()), |generator_config| {
+            let mut rand = generator_config.rng();
+            let mut look = generator_config.stream_rng(RandomStream::Look);
+            if self.area() <= 0. {
+                return Grass::default();
+            }
+            let mut blade = |(position, normal): (Vec3, Vec3), rand: &mut SmallRng| {
+                if generator_config.excludes(position)
+                    || !generator_config.accepts(position, normal, rand)
+                {
+                    return None;
+                }
+                Some(generator_config.blade(position, normal, rand, &mut look))
+            };
+            let blades = match generator_config.distribution {
+                Distribution::Uniform => {
+                    self.sample_uniform(generator_config.density, &mut rand, blade)
+                }
+                distribution => self
+                    .sample_triangles(distribution, generator_config.density, &mut rand)
+                    .into_iter()
+                    .filter_map(|position| blade(position, &mut rand))
+                    .collect(),
+            };
+            Grass { instances: blades }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::mesh::Indices;
+
+    #[test]
+    fn blades_follow_the_triangle_areas() {
+        let surface = MeshSurface::from_triangles(vec![
+            [Vec3::ZERO, Vec3::new(0., 0., 2.), Vec3::new(2., 0., 0.)],
+            [
+                Vec3::new(10., 0., 0.),
+                Vec3::new(10., 0., 4.),
+                Vec3::new(13., 0., 0.),
+            ],
+        ]);
+        assert!((surface.area() - 8.).abs() < 1e-5);
+        for distribution in [
+            Distribution::Uniform,
+            Distribution::JitteredGrid,
+            Distribution::PoissonDisk,
+            Distribution::HexGrid,
+        ] {
+            let grass = surface.generate_grass(StandardGeneratorConfig {
+                density: 200.,
+                distribution,
+                seed: Some(5),
+                ..Default::default()
+            });
+            let small = grass
+                .instances
+                .iter()
+                .filter(|blade| blade.position.x < 5.)
+                .count() as f32;
+            let large = grass.instances.len() as f32 - small;
+            // the large triangle has three times the area of the small one
+            assert!(
+                (large / small - 3.).abs() < 3. * 0.15,
+                "{distribution:?}: {small} and {large} blades"
+            );
+        }
+    }
+
+    #[test]
+    fn only_triangle_lists_are_read() {
+        let positions: Vec<[f32; 3]> = vec![[0., 0., 0.], [0., 0., 1.], [1., 0., 0.], [1., 0., 1.]];
+        let mesh = |topology| {
+            let mut mesh = Mesh::new(topology);
+            mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions.clone());
+            mesh.set_indices(Some(Indices::U32(vec![0, 1, 2, 2, 1, 3])));
+            mesh
+        };
+        let surface = MeshSurface::from_mesh(&mesh(PrimitiveTopology::TriangleList));
+        assert!((surface.expect("triangle list").area() - 1.).abs() < 1e-5);
+        for topology in [
+            PrimitiveTopology::PointList,
+            PrimitiveTopology::LineList,
+            PrimitiveTopology::LineStrip,
+            PrimitiveTopology::TriangleStrip,
+        ] {
+            assert!(MeshSurface::from_mesh(&mesh(topology)).is_none());
+        }
+    }
+
+    #[test]
+    fn contains_the_area_above_and_below_the_triangles() {
+        let surface = MeshSurface::from_triangles(vec![
+            [Vec3::ZERO, Vec3::new(0., 1., 2.), Vec3::new(2., 0., 0.)],
+            // a vertical wall doesn't cover anything
+            [Vec3::ZERO, Vec3::new(-2., 0., 0.), Vec3::new(-2., 2., 0.)],
+        ]);
+        assert!(surface.contains(Vec3::new(0.5, 5., 0.5)));
+        assert!(surface.contains(Vec3::new(0.5, -5., 0.5)));
+        assert!(!surface.contains(Vec3::new(1.5, 0., 1.5)));
+        assert!(!surface.contains(Vec3::new(-1., 0., 0.)));
+    }
+}
@@ -0,0 +1,260 @@
+use super::mix_seed;
+use bevy::prelude::{Vec2, Vec3};
+
+/// The kind of procedural noise of a [`NoiseField`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NoiseKind {
+    /// Smooth gradient noise on a square grid
+    #[default]
+    Perlin,
+    /// Gradient noise on a triangular grid, with less visible grid artifacts than perlin noise
+    Simplex,
+    /// The distance to the nearest random feature point, which gives cell-like patches
+    Worley,
+}
+
+/// Seeded procedural noise on the XZ plane, returning values between `low` and `high`.
+///
+/// The noise is seeded from [`StandardGeneratorConfig::seed`](super::StandardGeneratorConfig::seed),
+/// unless it has its own `seed`, so the same seed always produces the same patches.
+/// Multiple octaves add finer details with decreasing strength (fractal brownian motion).
+#[derive(Debug, Clone)]
+pub struct NoiseField {
+    pub kind: NoiseKind,
+    /// The number of noise features per unit of distance
+    pub frequency: f32,
+    /// The number of layered octaves, at least 1
+    pub octaves: u32,
+    /// The factor by which the frequency increases for each octave
+    pub lacunarity: f32,
+    /// The factor by which the strength decreases for each octave
+    pub persistence: f32,
+    /// Moves the noise on the XZ plane
+    pub offset: Vec2,
+    /// The value of the darkest spots of the noise
+    pub low: f32,
+    /// The value of the brightest spots of the noise
+    pub high: f32,
+    /// An optional seed replacing the seed of the generator config.
+    ///
+    /// Generators using different seeds, like the chunks of [`GrassStreaming`](crate::streaming::GrassStreaming),
+    /// share the same continuous patches with a fixed seed
+    pub seed: Option<u64>,
+}
+
+impl Default for NoiseField {
+    fn default() -> Self {
+        NoiseField {
+            kind: NoiseKind::Perlin,
+            frequency: 0.1,
+            octaves: 1,
+            lacunarity: 2.,
+            persistence: 0.5,
+            offset: Vec2::ZERO,
+            low: 0.,
+            high: 1.,
+            seed: None,
+        }
+    }
+}
+
+impl NoiseField {
+    pub fn new(kind: NoiseKind, frequency: f32) -> Self {
+        NoiseField {
+            kind,
+            frequency,
+            ..Default::default()
+        }
+    }
+    /// Samples the noise at `position`, `seed` is only used if the noise has no seed of its own
+    pub fn sample(&self, position: Vec3, seed: u64) -> f32 {
+        let seed = self.seed.unwrap_or(seed);
+        let point = (Vec2::new(position.x, position.z) + self.offset) * self.frequency;
+        let mut frequency = 1.;
+        let mut amplitude = 1.;
+        let mut total = 0.;
+        let mut amplitudes = 0.;
+        for octave in 0..self.octaves.max(1) {
+            // every octave gets its own seed, so the layers don't line up
+            let seed = mix_seed(seed, u64::from(octave));
+            let value = match self.kind {
+                NoiseKind::Perlin => perlin(point * frequency, seed),
+                NoiseKind::Simplex => simplex(point * frequency, seed),
+                NoiseKind::Worley => worley(point * frequency, seed),
+            };
+            total += value * amplitude;
+            amplitudes += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.persistence;
+        }
+        let value = (total / amplitudes).clamp(0., 1.);
+        self.low + (self.high - self.low) * value
+    }
+}
+
+/// A random number for a grid cell
+fn hash_cell(x: i32, y: i32, seed: u64) -> u64 {
+    mix_seed(seed, ((x as u32 as u64) << 32) | y as u32 as u64)
+}
+
+/// A random unit gradient for a grid cell
+fn gradient(x: i32, y: i32, seed: u64) -> Vec2 {
+    let angle = (hash_cell(x, y, seed) >> 40) as f32 / (1u64 << 24) as f32 * std::f32::consts::TAU;
+    Vec2::new(angle.cos(), angle.sin())
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+/// Perlin noise remapped to 0..=1
+fn perlin(point: Vec2, seed: u64) -> f32 {
+    let cell = point.floor();
+    let (x, y) = (cell.x as i32, cell.y as i32);
+    let local = point - cell;
+    let dot = |dx: i32, dy: i32| {
+        gradient(x + dx, y + dy, seed).dot(local - Vec2::new(dx as f32, dy as f32))
+    };
+    let (u, v) = (fade(local.x), fade(local.y));
+    let bottom = dot(0, 0) + (dot(1, 0) - dot(0, 0)) * u;
+    let top = dot(0, 1) + (dot(1, 1) - dot(0, 1)) * u;
+    let value = bottom + (top - bottom) * v;
+    // 2d perlin noise with unit gradients lies within ±sqrt(0.5)
+    value * std::f32::consts::FRAC_1_SQRT_2 + 0.5
+}
+
+/// Simplex noise remapped to 0..=1
+fn simplex(point: Vec2, seed: u64) -> f32 {
+    let skew = (3f32.sqrt() - 1.) / 2.;
+    let unskew = (3. - 3f32.sqrt()) / 6.;
+    let skewed = (point + (point.x + point.y) * skew).floor();
+    let origin = skewed - (skewed.x + skewed.y) * unskew;
+    let local = point - origin;
+    // the triangle of the simplex grid containing the point
+    let corner = if local.x > local.y { Vec2::X } else { Vec2::Y };
+    let offsets = [
+        local,
+        local - corner + unskew,
+        local - Vec2::ONE + 2. * unskew,
+    ];
+    let cells = [Vec2::ZERO, corner, Vec2::ONE];
+    let mut value = 0.;
+    for (offset, cell) in offsets.iter().zip(cells) {
+        let falloff = 0.5 - offset.length_squared();
+        if falloff > 0. {
+            let cell = skewed + cell;
+            let gradient = gradient(cell.x as i32, cell.y as i32, seed);
+            value += falloff.powi(4) * gradient.dot(*offset);
+        }
+    }
+    // scales the result to about ±1
+    (value * 70. * 0.5 + 0.5).clamp(0., 1.)
+}
+
+/// Distance to the nearest feature point, clamped to 0..=1
+fn worley(point: Vec2, seed: u64) -> f32 {
+    let cell = point.floor();
+    let mut nearest = f32::MAX;
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            let (x, y) = (cell.x as i32 + dx, cell.y as i32 + dy);
+            let hash = hash_cell(x, y, seed);
+            let feature = Vec2::new(
+                (hash & 0xFFFF) as f32 / 65535.,
+                ((hash >> 16) & 0xFFFF) as f32 / 65535.,
+            );
+            let feature = Vec2::new(x as f32, y as f32) + feature;
+            nearest = nearest.min(feature.distance(point));
+        }
+    }
+    nearest.min(1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [NoiseKind; 3] = [NoiseKind::Perlin, NoiseKind::Simplex, NoiseKind::Worley];
+
+    fn points() -> impl Iterator<Item = Vec3> {
+        (0..400).map(|i| Vec3::new((i % 20) as f32 * 1.37, 0., (i / 20) as f32 * 0.91))
+    }
+
+    #[test]
+    fn values_stay_between_low_and_high() {
+        for kind in KINDS {
+            let noise = NoiseField {
+                octaves: 3,
+                low: 0.2,
+                high: 0.8,
+                ..NoiseField::new(kind, 0.7)
+            };
+            for point in points() {
+                let value = noise.sample(point, 0);
+                assert!((0.2..=0.8).contains(&value), "{kind:?}: {value}");
+            }
+        }
+    }
+
+    #[test]
+    fn noise_is_reproducible_and_depends_on_the_config_seed() {
+        for kind in KINDS {
+            let noise = NoiseField::new(kind, 0.5);
+            assert!(points().all(|point| noise.sample(point, 3) == noise.clone().sample(point, 3)));
+            assert!(
+                points().any(|point| noise.sample(point, 3) != noise.sample(point, 4)),
+                "{kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn own_seed_replaces_the_config_seed() {
+        for kind in KINDS {
+            let noise = NoiseField {
+                seed: Some(5),
+                ..NoiseField::new(kind, 0.5)
+            };
+            let unseeded = NoiseField::new(kind, 0.5);
+            assert!(points().all(|point| noise.sample(point, 3) == noise.sample(point, 4)));
+            assert!(points().all(|point| noise.sample(point, 3) == unseeded.sample(point, 5)));
+        }
+    }
+
+    #[test]
+    fn noise_is_continuous() {
+        for kind in KINDS {
+            let noise = NoiseField::new(kind, 0.5);
+            for point in points() {
+                let step = noise.sample(point + Vec3::X * 1e-3, 0) - noise.sample(point, 0);
+                assert!(step.abs() < 0.01, "{kind:?} jumps by {step} at {point}");
+            }
+        }
+    }
+
+    #[test]
+    fn offset_moves_the_noise() {
+        let noise = NoiseField::new(NoiseKind::Perlin, 0.5);
+        let moved = NoiseField {
+            offset: Vec2::new(3., -2.),
+            ..noise.clone()
+        };
+        for point in points() {
+            let expected = noise.sample(point + Vec3::new(3., 0., -2.), 0);
+            assert!((moved.sample(point, 0) - expected).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn noise_varies_over_the_area() {
+        for kind in KINDS {
+            let noise = NoiseField::new(kind, 0.5);
+            let (min, max) = points()
+                .map(|point| noise.sample(point, 0))
+                .fold((f32::MAX, f32::MIN), |(min, max), value| {
+                    (min.min(value), max.max(value))
+                });
+            assert!(max - min > 0.3, "{kind:?} only spans {min}..{max}");
+        }
+    }
+}
@@ -1,41 +1,199 @@
-use super::{GrassGenerator, StandardGeneratorConfig};
-use crate::{grass::GrassBlade, Grass};
-use bevy::prelude::{Transform, Vec3};
-use rand::{rngs::SmallRng, Rng, SeedableRng};
+use super::{GrassArea, GrassGenerator, RandomStream, StandardGeneratorConfig};
+use crate::Grass;
+use bevy::prelude::{Transform, Vec2, Vec3};
+use std::sync::Arc;
+
+/// A function returning the height of a [`Plane`] at a point of its local XZ plane
+pub type PlaneHeight = Arc<dyn Fn(Vec2) -> f32 + Send + Sync>;
+
+/// An oriented rectangle filled with grass.
+///
+/// The rectangle lies on the local XZ plane, centered around the local origin,
+/// and is placed with `transform` like any other bevy entity:
+/// the translation is the center, the rotation orients the plane and the scale stretches it.
+#[derive(Clone)]
 pub struct Plane {
-    pub dimensions: Transform,
+    /// The extents of the rectangle on its local X and Z axis
+    pub size: Vec2,
+    /// The placement of the rectangle
+    pub transform: Transform,
+    /// An optional height on the local Y axis for each point of the local XZ plane.
+    ///
+    /// If [None] is used, the plane is flat
+    pub height: Option<PlaneHeight>,
 }
+
+impl Plane {
+    /// Creates a flat plane of `size` centered around the origin
+    pub fn new(size: Vec2) -> Self {
+        Plane {
+            size,
+            transform: Transform::IDENTITY,
+            height: None,
+        }
+    }
+    /// Places the plane with `transform`
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+    /// Sets the height function of the plane, see [`Plane::height`]
+    pub fn with_height(mut self, height: impl Fn(Vec2) -> f32 + Send + Sync + 'static) -> Self {
+        self.height = Some(Arc::new(height));
+        self
+    }
+    /// Creates a plane with the behavior of the previous `Plane { dimensions }`.
+    ///
+    /// The plane spans from the origin to the translation of `dimensions` on the XZ plane,
+    /// and the height rises linearly with the translation on the Y axis from the origin to the far corner.
+    /// For dimensions with only a translation and the default uniform distribution,
+    /// the same seed creates the positions and heights of the previous plane.
+    /// Rotation and scale of `dimensions` are applied around the center like any transform.
+    pub fn legacy(dimensions: Transform) -> Self {
+        let corner = dimensions.translation;
+        let size = Vec2::new(corner.x.abs(), corner.z.abs());
+        let mut transform = dimensions;
+        transform.translation = Vec3::new(corner.x / 2., 0., corner.z / 2.);
+        // mirror the plane for negative dimensions, so it still starts at the origin
+        transform.scale *= Vec3::new(corner.x.signum(), 1., corner.z.signum());
+        Plane {
+            size,
+            transform,
+            height: Some(Arc::new(move |point: Vec2| {
+                let relative = point / size + 0.5;
+                corner.y * (relative.x + relative.y)
+            })),
+        }
+    }
+    /// The local height at a point of the local XZ plane
+    fn local_height(&self, point: Vec2) -> f32 {
+        match &self.height {
+            Some(height) => height(point),
+            None => 0.,
+        }
+    }
+    /// The surface normal at a point of the local XZ plane in the space of the generated grass
+    fn normal_at(&self, point: Vec2) -> Vec3 {
+        let local_normal = match &self.height {
+            Some(height) => {
+                let step = (self.size.max_element() * 1e-3).max(1e-4);
+                let dx = height(point + Vec2::X * step) - height(point - Vec2::X * step);
+                let dz = height(point + Vec2::Y * step) - height(point - Vec2::Y * step);
+                Vec3::new(-dx, 2. * step, -dz)
+            }
+            None => Vec3::Y,
+        };
+        // normals are transformed with the inverse transpose, which divides by the scale
+        (self.transform.rotation * (local_normal / self.transform.scale)).normalize_or_zero()
+    }
+}
+
 impl GrassGenerator<StandardGeneratorConfig> for Plane {
     fn generate_grass(&self, generator_config: StandardGeneratorConfig) -> Grass {
-        let mut rand = if let Some(seed) = generator_config.seed {
-            SmallRng::seed_from_u64(seed)
-        } else {
-            SmallRng::from_entropy()
+        // the density applies to the scaled area
+        let scale = Vec2::new(self.transform.scale.x, self.transform.scale.z).abs();
+        let scaled_size = self.size.abs() * scale;
+        generator_config.within_budget(Some(scaled_size.x * scaled_size.y), |generator_config| {
+            let mut rand = generator_config.rng();
+            let mut look = generator_config.stream_rng(RandomStream::Look);
+            let blades = generator_config.distribution.sample_map(
+                scaled_size,
+                generator_config.density,
+                &mut rand,
+                |point, rand| {
+                    // back to the local plane, centered around the origin
+                    let local = point / scale - self.size.abs() / 2.;
+                    let position = self.transform.transform_point(Vec3::new(
+                        local.x,
+                        self.local_height(local),
+                        local.y,
+                    ));
+                    if generator_config.excludes(position) {
+                        return None;
+                    }
+                    let normal = self.normal_at(local);
+                    if !generator_config.accepts(position, normal, rand) {
+                        return None;
+                    }
+                    // collect as GrassBlade
+                    Some(generator_config.blade(position, normal, rand, &mut look))
+                },
+            );
+            Grass { instances: blades }
+        })
+    }
+}
+
+impl GrassArea for Plane {
+    /// Checks if the position lies above or below the rectangle
+    fn contains(&self, position: Vec3) -> bool {
+        let local = self
+            .transform
+            .compute_affine()
+            .inverse()
+            .transform_point3(position);
+        let half_size = self.size.abs() / 2.;
+        local.x.abs() <= half_size.x && local.z.abs() <= half_size.y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::color::ColorVariation;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    #[test]
+    fn legacy_matches_the_previous_plane() {
+        let dimensions = Transform::from_xyz(12., 3., -8.);
+        let config = StandardGeneratorConfig {
+            seed: Some(42),
+            ..Default::default()
         };
-        let area = self.dimensions.translation.x.abs() * self.dimensions.translation.z.abs();
-        let blades_count = (area * generator_config.density) as usize;
-        let blades = (0..blades_count)
-            .into_iter()
-            // generate random values and offset them
+        let grass = Plane::legacy(dimensions).generate_grass(config.clone());
+
+        // the generator of the previous plane
+        let mut rand = SmallRng::seed_from_u64(42);
+        let count = (12. * 8. * config.density) as usize;
+        let expected: Vec<(Vec3, f32)> = (0..count)
             .map(|_| {
-                let (x, z, mut height_deviation): (f32, f32, f32) = rand.gen();
-                height_deviation =
-                    (height_deviation - 0.5) * 2. * generator_config.height_deviation;
-                let y = x + z;
-                let height = generator_config.height + height_deviation;
-                (x, y, z, height)
+                let (x, z, height_deviation): (f32, f32, f32) = rand.gen();
+                let height_deviation = (height_deviation - 0.5) * 2. * config.height_deviation;
+                let position = dimensions.translation * Vec3::new(x, x + z, z);
+                (position, config.height + height_deviation)
             })
-            // apply plane transformations
-            .map(|(x, y, z, height)| {
-                let mut point = Vec3::new(x, y, z);
-                point = self.dimensions.scale * point;
-                point = self.dimensions.rotation * point;
-                point = self.dimensions.translation * point;
-                (point, height)
-            })
-            // collect as GrassBlade
-            .map(|(position, height)| GrassBlade { position, height })
             .collect();
-        Grass { instances: blades }
+
+        assert_eq!(grass.instances.len(), expected.len());
+        for (blade, (position, height)) in grass.instances.iter().zip(expected) {
+            assert!(
+                blade.position.abs_diff_eq(position, 1e-4),
+                "{} != {position}",
+                blade.position
+            );
+            assert!((blade.height - height).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn look_attributes_keep_the_positions() {
+        let plane = Plane::new(Vec2::splat(10.));
+        let config = StandardGeneratorConfig {
+            seed: Some(3),
+            ..Default::default()
+        };
+        let plain = plane.generate_grass(config.clone());
+        let varied = plane.generate_grass(StandardGeneratorConfig {
+            width_deviation: 0.3,
+            lean: 0.2,
+            lean_deviation: 0.1,
+            color_variation: ColorVariation::Random(0.2),
+            ..config
+        });
+        assert_eq!(plain.instances.len(), varied.instances.len());
+        for (plain, varied) in plain.instances.iter().zip(&varied.instances) {
+            assert_eq!(plain.position, varied.position);
+            assert_eq!(plain.height, varied.height);
+        }
     }
 }
@@ -0,0 +1,367 @@
+use super::{GrassArea, GrassGenerator, RandomStream, StandardGeneratorConfig};
+use crate::Grass;
+use bevy::prelude::{Vec2, Vec3};
+
+/// Fills an arbitrary polygon on the XZ plane with grass.
+///
+/// The polygon may be concave and may contain holes.
+/// The x and y coordinates of the vertices are used as x and z coordinates in the world.
+#[derive(Debug, Clone, Default)]
+pub struct Polygon {
+    /// The vertices of the outer boundary, in any winding order
+    pub outline: Vec<Vec2>,
+    /// The vertices of areas inside the outline which stay empty
+    pub holes: Vec<Vec<Vec2>>,
+    /// Optional heights of the vertices, which are interpolated over the whole area.
+    ///
+    /// The heights of the outline vertices come first, followed by those of each hole in order.
+    /// Missing heights are treated as 0. If [None] is used, the polygon is flat at y=0
+    pub heights: Option<Vec<f32>>,
+}
+
+impl Polygon {
+    pub fn new(outline: Vec<Vec2>) -> Self {
+        Polygon {
+            outline,
+            ..Default::default()
+        }
+    }
+    /// Adds a hole to the polygon
+    pub fn with_hole(mut self, hole: Vec<Vec2>) -> Self {
+        self.holes.push(hole);
+        self
+    }
+    /// Sets the heights of the vertices, see [`Polygon::heights`]
+    pub fn with_heights(mut self, heights: Vec<f32>) -> Self {
+        self.heights = Some(heights);
+        self
+    }
+    /// The area covered by the polygon, not counting the holes.
+    ///
+    /// Only holes with all vertices inside the outline are subtracted,
+    /// holes crossing the outline or overlapping each other are not clipped.
+    pub fn area(&self) -> f32 {
+        let holes: f32 = self
+            .holes
+            .iter()
+            .filter(|hole| {
+                hole.iter()
+                    .all(|vertex| crosses_odd(&self.outline, *vertex))
+            })
+            .map(|hole| signed_area(hole).abs())
+            .sum();
+        (signed_area(&self.outline).abs() - holes).max(0.)
+    }
+    /// Checks if `point` is inside the outline and outside of all holes
+    pub fn contains(&self, point: Vec2) -> bool {
+        crosses_odd(&self.outline, point) && !self.holes.iter().any(|hole| crosses_odd(hole, point))
+    }
+    /// The interpolated height at `point`.
+    ///
+    /// The heights are interpolated with mean value coordinates,
+    /// which work with concave polygons and holes and match the vertex heights on the edges.
+    pub fn height_at(&self, point: Vec2) -> f32 {
+        match self.weighted_rings() {
+            Some(rings) => mean_value_height(&rings, point),
+            None => 0.,
+        }
+    }
+    /// The surface normal of the interpolated heights at `point`
+    pub fn normal_at(&self, point: Vec2) -> Vec3 {
+        match self.weighted_rings() {
+            Some(rings) => mean_value_normal(&rings, point, self.normal_step()),
+            None => Vec3::Y,
+        }
+    }
+    /// The distance used to measure the slope of the heights, relative to the size of the polygon
+    fn normal_step(&self) -> f32 {
+        let (min, max) = self.bounds();
+        ((max - min).max_element() * 1e-3).max(1e-4)
+    }
+    fn bounds(&self) -> (Vec2, Vec2) {
+        let min = self
+            .outline
+            .iter()
+            .fold(Vec2::splat(f32::MAX), |a, b| a.min(*b));
+        let max = self
+            .outline
+            .iter()
+            .fold(Vec2::splat(f32::MIN), |a, b| a.max(*b));
+        (min, max)
+    }
+
+    fn rings(&self) -> impl Iterator<Item = &Vec<Vec2>> {
+        std::iter::once(&self.outline).chain(self.holes.iter())
+    }
+    /// The vertices of every ring with their heights,
+    /// with the outline oriented counter-clockwise and the holes clockwise
+    fn weighted_rings(&self) -> Option<Vec<Vec<(Vec2, f32)>>> {
+        let heights = self.heights.as_ref()?;
+        let mut offset = 0;
+        let rings = self
+            .rings()
+            .enumerate()
+            .map(|(index, ring)| {
+                let mut weighted: Vec<_> = ring
+                    .iter()
+                    .enumerate()
+                    .map(|(i, vertex)| (*vertex, heights.get(offset + i).copied().unwrap_or(0.)))
+                    .collect();
+                offset += ring.len();
+                let counter_clockwise = signed_area(ring) > 0.;
+                let is_outline = index == 0;
+                if counter_clockwise != is_outline {
+                    weighted.reverse();
+                }
+                weighted
+            })
+            .collect();
+        Some(rings)
+    }
+}
+
+/// The area with the sign indicating the winding order, positive being counter-clockwise
+fn signed_area(ring: &[Vec2]) -> f32 {
+    (0..ring.len())
+        .map(|i| ring[i].perp_dot(ring[(i + 1) % ring.len()]))
+        .sum::<f32>()
+        / 2.
+}
+
+/// Checks if a ray from `point` along +x crosses the ring an odd number of times
+fn crosses_odd(ring: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    for i in 0..ring.len() {
+        let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+        if (a.y > point.y) != (b.y > point.y) {
+            let x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+            if point.x < x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// The largest sine of the angle between two vertices seen from a point, at which they count as collinear
+const COLLINEAR_SINE: f32 = 1e-6;
+
+fn mean_value_height(rings: &[Vec<(Vec2, f32)>], point: Vec2) -> f32 {
+    let mut weight_sum = 0.;
+    let mut height_sum = 0.;
+    for ring in rings.iter().filter(|ring| ring.len() >= 3) {
+        let n = ring.len();
+        let offsets: Vec<Vec2> = ring.iter().map(|(vertex, _)| *vertex - point).collect();
+        let distances: Vec<f32> = offsets.iter().map(|offset| offset.length()).collect();
+        // tangent of the half angle spanned by each edge
+        let mut tangents = Vec::with_capacity(n);
+        for i in 0..n {
+            let j = (i + 1) % n;
+            if distances[i] <= f32::EPSILON {
+                return ring[i].1;
+            }
+            let area = offsets[i].perp_dot(offsets[j]);
+            let dot = offsets[i].dot(offsets[j]);
+            // the area grows with the square of the coordinates, so it is compared relative to the distances
+            let collinear = area.abs() <= COLLINEAR_SINE * distances[i] * distances[j];
+            if collinear && dot < 0. {
+                // the point lies on the edge
+                let t = distances[i] / (distances[i] + distances[j]);
+                return ring[i].1 + (ring[j].1 - ring[i].1) * t;
+            }
+            // tan(a/2) = sin(a) / (1 + cos(a)), which stays defined for collinear points,
+            // where (r_i * r_j - dot) / area would divide zero by zero.
+            // a point on the extension of the edge sees it under an angle of 0
+            if collinear {
+                tangents.push(0.);
+            } else {
+                tangents.push(area / (distances[i] * distances[j] + dot));
+            }
+        }
+        for i in 0..n {
+            let previous = (i + n - 1) % n;
+            let weight = (tangents[previous] + tangents[i]) / distances[i];
+            weight_sum += weight;
+            height_sum += weight * ring[i].1;
+        }
+    }
+    if weight_sum.abs() <= f32::EPSILON {
+        return 0.;
+    }
+    height_sum / weight_sum
+}
+
+/// The normal of the interpolated heights with central differences
+fn mean_value_normal(rings: &[Vec<(Vec2, f32)>], point: Vec2, step: f32) -> Vec3 {
+    let height = |offset: Vec2| mean_value_height(rings, point + offset);
+    let dx = height(Vec2::X * step) - height(-Vec2::X * step);
+    let dz = height(Vec2::Y * step) - height(-Vec2::Y * step);
+    Vec3::new(-dx, 2. * step, -dz).normalize()
+}
+
+impl GrassArea for Polygon {
+    fn contains(&self, position: Vec3) -> bool {
+        Polygon::contains(self, Vec2::new(position.x, position.z))
+    }
+}
+
+impl GrassGenerator<StandardGeneratorConfig> for Polygon {
+    fn generate_grass(&self, generator_config: StandardGeneratorConfig) -> Grass {
+        generator_config.within_budget(Some(self.area()), |generator_config| {
+            let mut rand = generator_config.rng();
+            let mut look = generator_config.stream_rng(RandomStream::Look);
+            if self.outline.len() < 3 {
+                return Grass::default();
+            }
+            let (min, max) = self.bounds();
+            let step = self.normal_step();
+            let rings = self.weighted_rings();
+            let blades = generator_config.distribution.sample_map(
+                max - min,
+                generator_config.density,
+                &mut rand,
+                |point, rand| {
+                    let point = point + min;
+                    if !self.contains(point) {
+                        return None;
+                    }
+                    let y = match &rings {
+                        Some(rings) => mean_value_height(rings, point),
+                        None => 0.,
+                    };
+                    let position = Vec3::new(point.x, y, point.y);
+                    if generator_config.excludes(position) {
+                        return None;
+                    }
+                    let normal = match &rings {
+                        Some(rings) => mean_value_normal(rings, point, step),
+                        None => Vec3::Y,
+                    };
+                    if !generator_config.accepts(position, normal, rand) {
+                        return None;
+                    }
+                    Some(generator_config.blade(position, normal, rand, &mut look))
+                },
+            );
+            Grass { instances: blades }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(min: f32, max: f32) -> Vec<Vec2> {
+        vec![
+            Vec2::new(min, min),
+            Vec2::new(max, min),
+            Vec2::new(max, max),
+            Vec2::new(min, max),
+        ]
+    }
+
+    /// An L shape, where (1, 2) lies on the extension of the inner edge from (4, 2) to (2, 2)
+    fn l_shape() -> Vec<Vec2> {
+        vec![
+            Vec2::new(0., 0.),
+            Vec2::new(4., 0.),
+            Vec2::new(4., 2.),
+            Vec2::new(2., 2.),
+            Vec2::new(2., 4.),
+            Vec2::new(0., 4.),
+        ]
+    }
+
+    #[test]
+    fn contains_respects_holes() {
+        let polygon = Polygon::new(square(0., 4.)).with_hole(square(1., 2.));
+        assert!(polygon.contains(Vec2::new(3., 3.)));
+        assert!(!polygon.contains(Vec2::new(1.5, 1.5)));
+        assert!(!polygon.contains(Vec2::new(5., 3.)));
+    }
+
+    #[test]
+    fn holes_outside_of_the_outline_stay_empty() {
+        let polygon = Polygon::new(square(0., 4.)).with_hole(square(10., 12.));
+        assert!(!polygon.contains(Vec2::new(11., 11.)));
+        assert!(polygon.contains(Vec2::new(1., 1.)));
+    }
+
+    #[test]
+    fn area_ignores_holes_outside_of_the_outline() {
+        let polygon = Polygon::new(square(0., 4.)).with_hole(square(10., 12.));
+        assert!((polygon.area() - 16.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn contains_concave_outlines() {
+        let polygon = Polygon::new(l_shape());
+        assert!(polygon.contains(Vec2::new(1., 3.)));
+        assert!(polygon.contains(Vec2::new(3., 1.)));
+        assert!(!polygon.contains(Vec2::new(3., 3.)));
+    }
+
+    #[test]
+    fn area_subtracts_holes() {
+        let polygon = Polygon::new(square(0., 4.)).with_hole(square(1., 2.));
+        assert!((polygon.area() - 15.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn heights_match_the_vertices_and_edges() {
+        let polygon = Polygon::new(square(0., 4.)).with_heights(vec![0., 1., 2., 3.]);
+        assert!((polygon.height_at(Vec2::new(4., 0.)) - 1.).abs() < 1e-5);
+        assert!((polygon.height_at(Vec2::new(2., 0.)) - 0.5).abs() < 1e-5);
+        assert!(polygon.height_at(Vec2::new(2., 2.)).is_finite());
+    }
+
+    #[test]
+    fn heights_reproduce_linear_slopes() {
+        let outline = l_shape();
+        let heights = outline.iter().map(|v| v.x + 2. * v.y).collect();
+        let polygon = Polygon::new(outline).with_heights(heights);
+        for point in [Vec2::new(1., 1.), Vec2::new(3., 1.), Vec2::new(1., 3.)] {
+            let height = polygon.height_at(point);
+            assert!(
+                (height - (point.x + 2. * point.y)).abs() < 1e-3,
+                "{point}: {height}"
+            );
+        }
+    }
+
+    #[test]
+    fn heights_work_at_any_scale() {
+        for scale in [1e-3, 500.] {
+            let outline = l_shape();
+            let heights = outline.iter().map(|v| v.x + 2. * v.y).collect();
+            let outline = outline.into_iter().map(|v| v * scale).collect();
+            let polygon = Polygon::new(outline).with_heights(heights);
+            for point in [Vec2::new(1., 1.), Vec2::new(3., 1.), Vec2::new(1., 3.)] {
+                let height = polygon.height_at(point * scale);
+                assert!(
+                    (height - (point.x + 2. * point.y)).abs() < 1e-3,
+                    "{scale}: {point}: {height}"
+                );
+            }
+            // a point on the slanted edge, which isn't exactly representable
+            let triangle = vec![Vec2::ZERO, Vec2::new(10., 3.), Vec2::new(0., 3.)];
+            let triangle = triangle.into_iter().map(|v| v * scale).collect();
+            let polygon = Polygon::new(triangle).with_heights(vec![0., 3., 6.]);
+            let height = polygon.height_at(Vec2::new(10., 3.) * scale / 3.);
+            assert!((height - 1.).abs() < 1e-3, "{scale}: {height}");
+        }
+    }
+
+    #[test]
+    fn heights_are_defined_on_edge_extensions() {
+        let outline = l_shape();
+        let heights = outline.iter().map(|v| v.x + 2. * v.y).collect();
+        let polygon = Polygon::new(outline).with_heights(heights);
+        let height = polygon.height_at(Vec2::new(1., 2.));
+        assert!((height - 5.).abs() < 1e-3, "{height}");
+        let normal = polygon.normal_at(Vec2::new(1., 2.));
+        assert!(normal.is_finite());
+        assert!((normal - Vec3::new(-1., 1., -2.).normalize()).length() < 1e-2);
+    }
+}
@@ -0,0 +1,309 @@
+use super::{GrassArea, GrassGenerator, RandomStream, StandardGeneratorConfig};
+use crate::Grass;
+use bevy::prelude::{Vec2, Vec3};
+use rand::Rng;
+use std::f32::consts::TAU;
+use std::fmt;
+use std::sync::Arc;
+
+/// How the density of a radial generator changes from its center to its outer edge
+#[derive(Clone, Default)]
+pub enum Falloff {
+    /// The density stays the same over the whole area
+    #[default]
+    None,
+    /// The density decreases linearly to 0 at the edge
+    Linear,
+    /// The density decreases smoothly to 0 at the edge, staying high around the center
+    Smooth,
+    /// The density decreases with `exp(-factor * t)`, where t is 0 at the center and 1 at the edge
+    Exponential(f32),
+    /// A custom curve, mapping the distance from the center (0) to the edge (1) to a density between 0 and 1
+    Custom(Arc<dyn Fn(f32) -> f32 + Send + Sync>),
+}
+
+impl fmt::Debug for Falloff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Falloff::None => write!(f, "None"),
+            Falloff::Linear => write!(f, "Linear"),
+            Falloff::Smooth => write!(f, "Smooth"),
+            Falloff::Exponential(factor) => f.debug_tuple("Exponential").field(factor).finish(),
+            Falloff::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl Falloff {
+    /// The relative density at `t`, going from 0 at the center to 1 at the edge
+    pub fn density(&self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        let density = match self {
+            Falloff::None => 1.,
+            Falloff::Linear => 1. - t,
+            Falloff::Smooth => 1. - t * t * (3. - 2. * t),
+            Falloff::Exponential(factor) => (-factor * t).exp(),
+            Falloff::Custom(curve) => curve(t),
+        };
+        density.clamp(0., 1.)
+    }
+}
+
+/// A filled circle around the local origin on the XZ plane
+#[derive(Debug, Clone, Default)]
+pub struct Disc {
+    pub radius: f32,
+    pub falloff: Falloff,
+}
+
+/// A ring around the local origin on the XZ plane.
+///
+/// The falloff goes from the inner to the outer radius
+#[derive(Debug, Clone, Default)]
+pub struct Annulus {
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub falloff: Falloff,
+}
+
+/// A slice of a circle around the local origin on the XZ plane.
+///
+/// Angles are in radians, measured from the X axis towards the Z axis
+#[derive(Debug, Clone, Default)]
+pub struct Sector {
+    pub radius: f32,
+    /// The angle where the sector starts
+    pub start_angle: f32,
+    /// The angle covered by the sector, starting at `start_angle`
+    pub angle: f32,
+    pub falloff: Falloff,
+}
+
+impl GrassGenerator<StandardGeneratorConfig> for Disc {
+    fn generate_grass(&self, generator_config: StandardGeneratorConfig) -> Grass {
+        let shape = RadialShape {
+            inner_radius: 0.,
+            outer_radius: self.radius,
+            start_angle: 0.,
+            angle: TAU,
+        };
+        shape.generate(&self.falloff, generator_config)
+    }
+}
+
+impl GrassGenerator<StandardGeneratorConfig> for Annulus {
+    fn generate_grass(&self, generator_config: StandardGeneratorConfig) -> Grass {
+        let shape = RadialShape {
+            inner_radius: self.inner_radius,
+            outer_radius: self.outer_radius,
+            start_angle: 0.,
+            angle: TAU,
+        };
+        shape.generate(&self.falloff, generator_config)
+    }
+}
+
+impl GrassGenerator<StandardGeneratorConfig> for Sector {
+    fn generate_grass(&self, generator_config: StandardGeneratorConfig) -> Grass {
+        let shape = RadialShape {
+            inner_radius: 0.,
+            outer_radius: self.radius,
+            start_angle: self.start_angle,
+            angle: self.angle,
+        };
+        shape.generate(&self.falloff, generator_config)
+    }
+}
+
+impl GrassArea for Disc {
+    fn contains(&self, position: Vec3) -> bool {
+        Vec2::new(position.x, position.z).length() <= self.radius
+    }
+}
+
+impl GrassArea for Annulus {
+    fn contains(&self, position: Vec3) -> bool {
+        let distance = Vec2::new(position.x, position.z).length();
+        distance >= self.inner_radius && distance <= self.outer_radius
+    }
+}
+
+impl GrassArea for Sector {
+    fn contains(&self, position: Vec3) -> bool {
+        let shape = RadialShape {
+            inner_radius: 0.,
+            outer_radius: self.radius,
+            start_angle: self.start_angle,
+            angle: self.angle,
+        };
+        shape
+            .relative_distance(Vec2::new(position.x, position.z))
+            .is_some()
+    }
+}
+
+/// A slice of a ring, which can express all radial generators
+struct RadialShape {
+    inner_radius: f32,
+    outer_radius: f32,
+    start_angle: f32,
+    angle: f32,
+}
+
+impl RadialShape {
+    /// Returns the relative distance between the inner and outer radius, if `point` is inside of the shape
+    fn relative_distance(&self, point: Vec2) -> Option<f32> {
+        let distance = point.length();
+        if distance < self.inner_radius || distance > self.outer_radius {
+            return None;
+        }
+        if self.angle < TAU {
+            let angle = (point.y.atan2(point.x) - self.start_angle).rem_euclid(TAU);
+            if angle > self.angle {
+                return None;
+            }
+        }
+        let width = self.outer_radius - self.inner_radius;
+        if width <= 0. {
+            return Some(0.);
+        }
+        Some((distance - self.inner_radius) / width)
+    }
+
+    /// The area covered by the shape
+    fn area(&self) -> f32 {
+        let radii = self.outer_radius.powi(2) - self.inner_radius.powi(2);
+        self.angle.clamp(0., TAU) / 2. * radii.max(0.)
+    }
+
+    fn generate(&self, falloff: &Falloff, generator_config: StandardGeneratorConfig) -> Grass {
+        generator_config.within_budget(Some(self.area()), |generator_config| {
+            let mut rand = generator_config.rng();
+            let mut look = generator_config.stream_rng(RandomStream::Look);
+            let radius = self.outer_radius.abs();
+            let blades = generator_config.distribution.sample_map(
+                Vec2::splat(radius * 2.),
+                generator_config.density,
+                &mut rand,
+                |point, rand| {
+                    let point = point - radius;
+                    let t = self.relative_distance(point)?;
+                    let position = Vec3::new(point.x, 0., point.y);
+                    if generator_config.excludes(position) {
+                        return None;
+                    }
+                    // a constant falloff keeps every blade without drawing a random number
+                    if !matches!(falloff, Falloff::None) && rand.gen::<f32>() >= falloff.density(t)
+                    {
+                        return None;
+                    }
+                    if !generator_config.accepts(position, Vec3::Y, rand) {
+                        return None;
+                    }
+                    Some(generator_config.blade(position, Vec3::Y, rand, &mut look))
+                },
+            );
+            Grass { instances: blades }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    fn at_angle(angle: f32, distance: f32) -> Vec2 {
+        Vec2::new(angle.cos(), angle.sin()) * distance
+    }
+
+    #[test]
+    fn falloffs_go_from_the_center_to_the_edge() {
+        for falloff in [Falloff::Linear, Falloff::Smooth, Falloff::Exponential(2.)] {
+            assert!((falloff.density(0.) - 1.).abs() < 1e-6, "{falloff:?}");
+            assert!(
+                falloff.density(0.5) < 1. && falloff.density(0.5) > 0.,
+                "{falloff:?}"
+            );
+            assert!(falloff.density(0.9) < falloff.density(0.5), "{falloff:?}");
+        }
+        assert_eq!(Falloff::None.density(0.7), 1.);
+        assert!((Falloff::Linear.density(0.25) - 0.75).abs() < 1e-6);
+        assert!((Falloff::Smooth.density(0.5) - 0.5).abs() < 1e-6);
+        assert!((Falloff::Exponential(2.).density(0.5) - (-1f32).exp()).abs() < 1e-6);
+        assert_eq!(Falloff::Linear.density(1.), 0.);
+        assert_eq!(Falloff::Smooth.density(1.), 0.);
+    }
+
+    #[test]
+    fn falloffs_are_clamped() {
+        // t outside of the shape is clamped to the center or the edge
+        assert_eq!(Falloff::Linear.density(-1.), 1.);
+        assert_eq!(Falloff::Linear.density(2.), 0.);
+        let curve = Falloff::Custom(Arc::new(|t| 3. - 4. * t));
+        assert_eq!(curve.density(0.), 1.);
+        assert_eq!(curve.density(1.), 0.);
+        assert!((curve.density(0.6) - 0.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn annulus_measures_between_the_radii() {
+        let shape = RadialShape {
+            inner_radius: 2.,
+            outer_radius: 4.,
+            start_angle: 0.,
+            angle: TAU,
+        };
+        assert!(shape.relative_distance(at_angle(1., 1.)).is_none());
+        assert!(shape.relative_distance(at_angle(1., 5.)).is_none());
+        let t = shape.relative_distance(at_angle(-2., 3.)).unwrap();
+        assert!((t - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn sector_angles_wrap_around() {
+        // all of these describe the quarter from -45° to 45°
+        for start_angle in [-FRAC_PI_4, TAU - FRAC_PI_4, 3. * TAU - FRAC_PI_4] {
+            let sector = Sector {
+                radius: 2.,
+                start_angle,
+                angle: FRAC_PI_2,
+                falloff: Falloff::None,
+            };
+            for inside in [0., 0.7, -0.7] {
+                let point = at_angle(inside, 1.);
+                assert!(
+                    sector.contains(Vec3::new(point.x, 0., point.y)),
+                    "{start_angle}: {inside}"
+                );
+            }
+            for outside in [1., -1., PI] {
+                let point = at_angle(outside, 1.);
+                assert!(
+                    !sector.contains(Vec3::new(point.x, 0., point.y)),
+                    "{start_angle}: {outside}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sector_blades_stay_inside() {
+        let sector = Sector {
+            radius: 5.,
+            start_angle: PI,
+            angle: PI,
+            falloff: Falloff::Linear,
+        };
+        let grass = sector.generate_grass(StandardGeneratorConfig {
+            seed: Some(2),
+            ..Default::default()
+        });
+        assert!(!grass.instances.is_empty());
+        // the half circle with negative z
+        assert!(grass
+            .instances
+            .iter()
+            .all(|blade| blade.position.z <= 1e-4 && blade.position.length() <= 5. + 1e-4));
+    }
+}
@@ -0,0 +1,135 @@
+use bevy::prelude::Vec3;
+use rand::Rng;
+
+/// Rules which remove or thin out blades depending on the surface they grow on.
+///
+/// The rules are applied by every standard generator with the surface normal of each blade,
+/// custom generators can use [`PlacementRules::accepts`] for the same filtering.
+/// Altitudes are measured on the Y axis in the space of the generated grass.
+#[derive(Debug, Clone, Default)]
+pub struct PlacementRules {
+    /// The steepest slope in radians which still gets grass, for example no grass on cliffs steeper than 40°
+    pub max_slope: Option<f32>,
+    /// The range in radians below `max_slope` over which the grass gets thinner, instead of stopping at a hard edge
+    pub slope_falloff: f32,
+    /// No grass is placed below this altitude
+    pub min_altitude: Option<f32>,
+    /// No grass is placed above this altitude, for example at the snowline
+    pub max_altitude: Option<f32>,
+    /// The range inside the altitude limits over which the grass gets thinner
+    pub altitude_falloff: f32,
+}
+
+impl PlacementRules {
+    /// The probability between 0 and 1 of a blade at `position` on a surface with `normal` being placed
+    pub fn density(&self, position: Vec3, normal: Vec3) -> f32 {
+        let mut density = 1.;
+        if let Some(max_slope) = self.max_slope {
+            let slope = normal
+                .normalize_or_zero()
+                .dot(Vec3::Y)
+                .clamp(-1., 1.)
+                .acos();
+            density *= band(max_slope - slope, self.slope_falloff);
+        }
+        if let Some(min_altitude) = self.min_altitude {
+            density *= band(position.y - min_altitude, self.altitude_falloff);
+        }
+        if let Some(max_altitude) = self.max_altitude {
+            density *= band(max_altitude - position.y, self.altitude_falloff);
+        }
+        density
+    }
+    /// Decides randomly whether a blade is placed, see [`PlacementRules::density`].
+    ///
+    /// Exactly one random number is drawn, so the following blades don't depend on the outcome
+    pub fn accepts(&self, position: Vec3, normal: Vec3, rand: &mut impl Rng) -> bool {
+        rand.gen::<f32>() < self.density(position, normal)
+    }
+    /// Returns true if no rule is set, so every blade is placed
+    pub fn is_empty(&self) -> bool {
+        self.max_slope.is_none() && self.min_altitude.is_none() && self.max_altitude.is_none()
+    }
+}
+
+/// 0 outside of a limit, rising linearly to 1 over `falloff` inside of it
+fn band(distance_inside: f32, falloff: f32) -> f32 {
+    if distance_inside < 0. {
+        0.
+    } else if falloff <= 0. {
+        1.
+    } else {
+        (distance_inside / falloff).min(1.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    /// A normal tilted by `slope` radians away from the Y axis
+    fn tilted(slope: f32) -> Vec3 {
+        Vec3::new(slope.sin(), slope.cos(), 0.)
+    }
+
+    #[test]
+    fn empty_rules_keep_everything() {
+        let rules = PlacementRules::default();
+        assert!(rules.is_empty());
+        assert_eq!(rules.density(Vec3::new(0., -100., 0.), Vec3::X), 1.);
+    }
+
+    #[test]
+    fn slopes_fade_out_below_the_limit() {
+        let hard = PlacementRules {
+            max_slope: Some(40f32.to_radians()),
+            ..Default::default()
+        };
+        assert!(!hard.is_empty());
+        assert_eq!(hard.density(Vec3::ZERO, tilted(30f32.to_radians())), 1.);
+        assert_eq!(hard.density(Vec3::ZERO, tilted(50f32.to_radians())), 0.);
+
+        let soft = PlacementRules {
+            slope_falloff: 20f32.to_radians(),
+            ..hard
+        };
+        assert_eq!(soft.density(Vec3::ZERO, tilted(10f32.to_radians())), 1.);
+        let half = soft.density(Vec3::ZERO, tilted(30f32.to_radians()));
+        assert!((half - 0.5).abs() < 1e-4, "{half}");
+        assert_eq!(soft.density(Vec3::ZERO, tilted(50f32.to_radians())), 0.);
+        // surfaces facing down are steeper than any limit
+        assert_eq!(soft.density(Vec3::ZERO, -Vec3::Y), 0.);
+    }
+
+    #[test]
+    fn altitudes_fade_out_inside_the_limits() {
+        let rules = PlacementRules {
+            min_altitude: Some(0.),
+            max_altitude: Some(10.),
+            altitude_falloff: 2.,
+            ..Default::default()
+        };
+        let density = |y: f32| rules.density(Vec3::new(3., y, -4.), Vec3::Y);
+        assert_eq!(density(-1.), 0.);
+        assert!((density(1.) - 0.5).abs() < 1e-6);
+        assert_eq!(density(5.), 1.);
+        assert!((density(9.5) - 0.25).abs() < 1e-6);
+        assert_eq!(density(11.), 0.);
+    }
+
+    #[test]
+    fn accepts_draws_exactly_one_number() {
+        let rules = PlacementRules {
+            max_altitude: Some(0.),
+            ..Default::default()
+        };
+        for position in [Vec3::Y, -Vec3::Y] {
+            let mut rand = SmallRng::seed_from_u64(4);
+            let mut expected = SmallRng::seed_from_u64(4);
+            rules.accepts(position, Vec3::Y, &mut rand);
+            expected.gen::<f32>();
+            assert_eq!(rand.gen::<u64>(), expected.gen::<u64>());
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use super::GrassGenerator;
+use crate::Grass;
+use bevy::prelude::*;
+use bevy::tasks::AsyncComputeTaskPool;
+use std::sync::{Arc, Mutex};
+
+/// Generates grass on the [`AsyncComputeTaskPool`] without blocking the main thread.
+///
+/// Once the generation is finished, the [`Grass`] is inserted into the entity,
+/// this component is removed and a [`GrassGenerated`] event is sent.
+/// It is usually spawned together with a [`GrassBundle`](crate::GrassBundle), which is empty until then.
+#[derive(Component)]
+pub struct GrassGenerationTask {
+    result: Arc<Mutex<Option<Grass>>>,
+}
+
+impl GrassGenerationTask {
+    /// Starts generating grass with `generator` in the background
+    pub fn new<G, C>(generator: G, config: C) -> Self
+    where
+        G: GrassGenerator<C> + Send + 'static,
+        C: Send + 'static,
+    {
+        let result = Arc::new(Mutex::new(None));
+        let task_result = result.clone();
+        AsyncComputeTaskPool::get()
+            .spawn(async move {
+                let grass = generator.generate_grass(config);
+                if let Ok(mut result) = task_result.lock() {
+                    *result = Some(grass);
+                }
+            })
+            // the result is read from the shared slot, which also works with the single threaded task pool on wasm
+            .detach();
+        GrassGenerationTask { result }
+    }
+    /// Takes the generated grass, if the generation is finished
+    fn take(&self) -> Option<Grass> {
+        self.result.lock().ok()?.take()
+    }
+}
+
+/// Sent when a [`GrassGenerationTask`] has finished and its [`Grass`] was inserted into `entity`
+#[derive(Debug, Clone, Copy)]
+pub struct GrassGenerated {
+    pub entity: Entity,
+}
+
+/// The progress of all [`GrassGenerationTask`]s, which can be used by loading screens
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct GrassGenerationProgress {
+    /// The number of tasks still running
+    pub pending: usize,
+    /// The number of tasks finished since the start of the app
+    pub finished: usize,
+}
+
+impl GrassGenerationProgress {
+    /// Returns true if no generation task is running
+    pub fn is_done(&self) -> bool {
+        self.pending == 0
+    }
+}
+
+pub(crate) fn poll_grass_generation_tasks(
+    mut commands: Commands,
+    tasks: Query<(Entity, &GrassGenerationTask)>,
+    mut progress: ResMut<GrassGenerationProgress>,
+    mut generated: EventWriter<GrassGenerated>,
+) {
+    progress.pending = 0;
+    for (entity, task) in tasks.iter() {
+        match task.take() {
+            Some(grass) => {
+                progress.finished += 1;
+                // the entity may have been despawned by a command of an earlier system
+                if let Some(mut entity_commands) = commands.get_entity(entity) {
+                    entity_commands
+                        .insert(grass)
+                        .remove::<GrassGenerationTask>();
+                    generated.send(GrassGenerated { entity });
+                }
+            }
+            None => progress.pending += 1,
+        }
+    }
+}
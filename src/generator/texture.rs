@@ -0,0 +1,158 @@
+//! Helpers to read values from [`Image`]s on the cpu.
+use bevy::prelude::{Image, Vec2};
+use bevy::render::render_resource::TextureFormat;
+
+/// Returns true if [`sample_bilinear`] can read images of the given format
+pub(crate) fn is_supported(format: TextureFormat) -> bool {
+    texel_layout(format).is_some()
+}
+
+/// Samples the red channel of `image` at `uv` with bilinear filtering.
+///
+/// The result is normalized to `0..=1`, `uv` is clamped to the edges of the image.
+/// Unsupported formats always return 0.
+/// Note that 8 bit srgb textures are read without any color space conversion,
+/// so grayscale values are used as they were painted.
+pub(crate) fn sample_bilinear(image: &Image, uv: Vec2) -> f32 {
+    let width = image.texture_descriptor.size.width;
+    let height = image.texture_descriptor.size.height;
+    if width == 0 || height == 0 {
+        return 0.;
+    }
+    // texel centers are at half coordinates
+    let x = (uv.x.clamp(0., 1.) * width as f32 - 0.5).max(0.);
+    let y = (uv.y.clamp(0., 1.) * height as f32 - 0.5).max(0.);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (tx, ty) = (x.fract(), y.fract());
+
+    let top = lerp(texel(image, x0, y0), texel(image, x1, y0), tx);
+    let bottom = lerp(texel(image, x0, y1), texel(image, x1, y1), tx);
+    lerp(top, bottom, ty)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Reads the normalized red channel of a single texel
+fn texel(image: &Image, x: u32, y: u32) -> f32 {
+    let (bytes_per_texel, red_offset, kind) = match texel_layout(image.texture_descriptor.format) {
+        Some(layout) => layout,
+        None => return 0.,
+    };
+    let index =
+        (y * image.texture_descriptor.size.width + x) as usize * bytes_per_texel + red_offset;
+    let bytes = match image.data.get(index..index + kind.size()) {
+        Some(bytes) => bytes,
+        None => return 0.,
+    };
+    match kind {
+        ChannelKind::Unorm8 => bytes[0] as f32 / u8::MAX as f32,
+        ChannelKind::Unorm16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / u16::MAX as f32,
+        ChannelKind::Float32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ChannelKind {
+    Unorm8,
+    Unorm16,
+    Float32,
+}
+
+impl ChannelKind {
+    fn size(self) -> usize {
+        match self {
+            ChannelKind::Unorm8 => 1,
+            ChannelKind::Unorm16 => 2,
+            ChannelKind::Float32 => 4,
+        }
+    }
+}
+
+/// Bytes per texel, byte offset of the red channel and how to read the red channel
+fn texel_layout(format: TextureFormat) -> Option<(usize, usize, ChannelKind)> {
+    use TextureFormat::*;
+    let layout = match format {
+        R8Unorm => (1, 0, ChannelKind::Unorm8),
+        Rg8Unorm => (2, 0, ChannelKind::Unorm8),
+        Rgba8Unorm | Rgba8UnormSrgb => (4, 0, ChannelKind::Unorm8),
+        Bgra8Unorm | Bgra8UnormSrgb => (4, 2, ChannelKind::Unorm8),
+        // 16 bit png images are loaded as uint textures
+        R16Unorm | R16Uint => (2, 0, ChannelKind::Unorm16),
+        Rg16Unorm | Rg16Uint => (4, 0, ChannelKind::Unorm16),
+        Rgba16Unorm | Rgba16Uint => (8, 0, ChannelKind::Unorm16),
+        R32Float => (4, 0, ChannelKind::Float32),
+        Rg32Float => (8, 0, ChannelKind::Float32),
+        Rgba32Float => (16, 0, ChannelKind::Float32),
+        _ => return None,
+    };
+    Some(layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::render_resource::{Extent3d, TextureDimension};
+
+    fn image(width: u32, height: u32, data: Vec<u8>, format: TextureFormat) -> Image {
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        Image::new(size, TextureDimension::D2, data, format)
+    }
+
+    #[test]
+    fn samples_texel_centers() {
+        let image = image(2, 1, vec![0, 255], TextureFormat::R8Unorm);
+        assert_eq!(sample_bilinear(&image, Vec2::new(0.25, 0.5)), 0.);
+        assert_eq!(sample_bilinear(&image, Vec2::new(0.75, 0.5)), 1.);
+    }
+
+    #[test]
+    fn interpolates_between_texels() {
+        let image = image(2, 2, vec![0, 255, 255, 255], TextureFormat::R8Unorm);
+        assert!((sample_bilinear(&image, Vec2::new(0.5, 0.25)) - 0.5).abs() < 1e-5);
+        assert!((sample_bilinear(&image, Vec2::new(0.5, 0.5)) - 0.75).abs() < 1e-5);
+    }
+
+    #[test]
+    fn clamps_to_the_edges() {
+        let image = image(2, 1, vec![0, 255], TextureFormat::R8Unorm);
+        assert_eq!(sample_bilinear(&image, Vec2::new(-3., 0.5)), 0.);
+        assert_eq!(sample_bilinear(&image, Vec2::new(3., 7.)), 1.);
+    }
+
+    #[test]
+    fn reads_the_red_channel() {
+        let rgba = image(1, 1, vec![51, 0, 0, 255], TextureFormat::Rgba8Unorm);
+        assert!((sample_bilinear(&rgba, Vec2::splat(0.5)) - 0.2).abs() < 1e-5);
+        let bgra = image(1, 1, vec![0, 0, 51, 255], TextureFormat::Bgra8UnormSrgb);
+        assert!((sample_bilinear(&bgra, Vec2::splat(0.5)) - 0.2).abs() < 1e-5);
+        let r16 = image(
+            1,
+            1,
+            u16::MAX.to_le_bytes().to_vec(),
+            TextureFormat::R16Uint,
+        );
+        assert_eq!(sample_bilinear(&r16, Vec2::splat(0.5)), 1.);
+        let r32 = image(
+            1,
+            1,
+            0.25f32.to_le_bytes().to_vec(),
+            TextureFormat::R32Float,
+        );
+        assert_eq!(sample_bilinear(&r32, Vec2::splat(0.5)), 0.25);
+    }
+
+    #[test]
+    fn unsupported_formats_read_zero() {
+        let format = TextureFormat::Rgba8Snorm;
+        assert!(!is_supported(format));
+        let image = image(1, 1, vec![127, 127, 127, 127], format);
+        assert_eq!(sample_bilinear(&image, Vec2::splat(0.5)), 0.);
+    }
+}
@@ -1,6 +1,9 @@
+use crate::displacement::GrassDisplacer;
+use crate::{GrassBundle, GrassWind};
 use bevy::prelude::*;
 use bevy::render::primitives::Aabb;
 use bevy::render::render_resource::ShaderType;
+use bevy::utils::HashMap;
 use bytemuck::{Pod, Zeroable};
 
 /// A collection of grassblades to be extracted later into the render world
@@ -19,15 +22,95 @@ impl Grass {
     pub fn calculate_aabb(&self) -> Aabb {
         let mut outer = Vec3::new(f32::MIN, f32::MIN, f32::MIN);
         let mut inner = Vec3::new(f32::MAX, f32::MAX, f32::MAX);
-        self.instances
-            .iter()
-            .map(|blade| (blade.position, blade.height))
-            .for_each(|(blade_pos, height)| {
-                inner = inner.min(blade_pos);
-                outer = outer.max(blade_pos + Vec3::Y * height);
-            });
+        self.instances.iter().for_each(|blade| {
+            let tip = blade.position
+                + Quat::from_rotation_arc(Vec3::Y, blade.up)
+                    * Vec3::new(blade.lean.x, 1., blade.lean.y)
+                    * blade.height;
+            inner = inner.min(blade.position).min(tip);
+            outer = outer.max(blade.position).max(tip);
+        });
         Aabb::from_min_max(inner, outer)
     }
+    /// Splits the grass blades into a grid of square cells on the XZ plane.
+    ///
+    /// The key of each chunk is the coordinate of its cell, empty cells are left out
+    pub fn split_into_chunks(&self, cell_size: f32) -> HashMap<IVec2, Grass> {
+        let mut chunks: HashMap<IVec2, Grass> = HashMap::default();
+        for blade in &self.instances {
+            let cell = (Vec2::new(blade.position.x, blade.position.z) / cell_size)
+                .floor()
+                .as_ivec2();
+            chunks.entry(cell).or_default().instances.push(*blade);
+        }
+        chunks
+    }
+}
+
+/// Splits the [`Grass`] of an entity into child entities with one chunk of grass each.
+///
+/// Each chunk gets its own tight [`Aabb`], so blades outside of the camera view can be culled.
+/// Whenever the [`Grass`] of the entity changes, the chunks are recreated.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct GrassChunking {
+    /// The size of the square cells on the XZ plane
+    pub cell_size: f32,
+}
+
+impl Default for GrassChunking {
+    fn default() -> Self {
+        GrassChunking { cell_size: 10. }
+    }
+}
+
+/// Marks a child entity created by [`GrassChunking`]
+#[derive(Component, Clone, Copy, Debug)]
+pub struct GrassChunk {
+    /// The cell coordinate of the chunk
+    pub cell: IVec2,
+}
+
+pub(crate) fn split_grass_into_chunks(
+    mut commands: Commands,
+    grasses: Query<
+        (
+            Entity,
+            &Grass,
+            &GrassChunking,
+            &Handle<Mesh>,
+            Option<&Children>,
+        ),
+        Changed<Grass>,
+    >,
+    chunks: Query<(), With<GrassChunk>>,
+) {
+    for (e, grass, chunking, mesh, children) in grasses.iter() {
+        // remove the chunks of the previous grass
+        if let Some(children) = children {
+            for child in children.iter() {
+                if chunks.contains(*child) {
+                    commands.entity(*child).despawn_recursive();
+                }
+            }
+        }
+        commands
+            .entity(e)
+            .remove::<Grass>()
+            .remove::<GrassBounds>()
+            .remove::<Aabb>()
+            .with_children(|parent| {
+                for (cell, grass) in grass.split_into_chunks(chunking.cell_size) {
+                    parent.spawn((
+                        GrassBundle {
+                            grass,
+                            grass_mesh: mesh.clone(),
+                            ..default()
+                        },
+                        GrassChunk { cell },
+                    ));
+                }
+            });
+    }
 }
 
 /// Representation of a single grassblade
@@ -36,6 +119,65 @@ impl Grass {
 pub struct GrassBlade {
     pub position: Vec3,
     pub height: f32,
+    /// The rotation of the blade around the Y axis in radians
+    pub rotation: f32,
+    /// The scale of the blade width
+    pub width: f32,
+    /// The direction and amount the blade leans towards on the XZ plane.
+    ///
+    /// The tip of the blade is moved by `lean * height`
+    pub lean: Vec2,
+    /// The tint multiplied with the main color of the region
+    pub color: Vec3,
+    /// The normalized direction the blade grows towards, usually the normal of the surface
+    pub up: Vec3,
+}
+
+impl GrassBlade {
+    /// Creates an upright blade without any rotation or tint
+    pub fn new(position: Vec3, height: f32) -> Self {
+        GrassBlade {
+            position,
+            height,
+            rotation: 0.,
+            width: 1.,
+            lean: Vec2::ZERO,
+            color: Vec3::ONE,
+            up: Vec3::Y,
+        }
+    }
+}
+
+/// The [`Aabb`] of the unbent blades of a [`Grass`] and their largest height.
+///
+/// Cached next to the grass, so a changing bend only pads the box instead of visiting every blade again
+#[derive(Component, Clone, Copy, Debug)]
+pub(crate) struct GrassBounds {
+    min: Vec3,
+    max: Vec3,
+    max_height: f32,
+}
+
+impl GrassBounds {
+    fn new(grass: &Grass) -> Self {
+        let aabb = grass.calculate_aabb();
+        GrassBounds {
+            min: aabb.min().into(),
+            max: aabb.max().into(),
+            max_height: grass
+                .instances
+                .iter()
+                .map(|blade| blade.height.abs())
+                .fold(0., f32::max),
+        }
+    }
+    /// The box containing the blades, while their tips are bent by up to `bend` per unit of blade height.
+    ///
+    /// The bend is applied in world space, so the box is padded in every direction
+    fn aabb_with_bend(&self, bend: f32) -> Aabb {
+        let padding = Vec3::splat((bend * self.max_height).abs());
+        Aabb::from_min_max(self.min - padding, self.max + padding)
+    }
 }
 
 /// To calculate frustum culling we need the [Aabb] box of the entity
@@ -43,10 +185,38 @@ pub struct GrassBlade {
 /// Note that it is in the responsabilty of the user to minimize the [Aabb] boxes of the chunks if high performance is needed
 pub(crate) fn add_aabb_box_to_grass(
     mut commands: Commands,
-    grasses: Query<(Entity, &Grass), Added<Grass>>,
+    // changed instead of added, since generated grass replaces the empty grass of the bundle
+    grasses: Query<(Entity, &Grass, Option<&GrassBounds>, ChangeTrackers<Grass>)>,
+    wind: Res<GrassWind>,
+    displacers: Query<&GrassDisplacer>,
+    mut last_bend: Local<f32>,
 ) {
-    for (e, grass) in grasses.iter() {
-        let aabb = grass.calculate_aabb();
-        commands.entity(e).insert(aabb);
+    // the shader bends the blades, so all boxes grow or shrink with the largest bend.
+    // overlapping displacers add up, but rarely cover the same blades
+    let displacement = displacers
+        .iter()
+        .map(|displacer| displacer.strength.abs())
+        .fold(0., f32::max);
+    let bend = wind.max_bend() + displacement;
+    let bend_changed = bend != *last_bend;
+    *last_bend = bend;
+    for (e, grass, bounds, grass_tracker) in grasses.iter() {
+        // empty grass, like the one of a bundle waiting for its generation task, has no bounds
+        if grass.instances.is_empty() {
+            continue;
+        }
+        match bounds {
+            Some(bounds) if !grass_tracker.is_changed() => {
+                if bend_changed {
+                    commands.entity(e).insert(bounds.aabb_with_bend(bend));
+                }
+            }
+            _ => {
+                let bounds = GrassBounds::new(grass);
+                commands
+                    .entity(e)
+                    .insert((bounds, bounds.aabb_with_bend(bend)));
+            }
+        }
     }
 }
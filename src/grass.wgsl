@@ -9,40 +9,195 @@ struct ShaderRegionConfig {
 @group(1) @binding(0)
 var<uniform> mesh: Mesh;
 
+struct ShaderWind {
+    // normalized direction on the xz plane
+    direction: vec2<f32>,
+    strength: f32,
+    gust_frequency: f32,
+    turbulence_scale: f32,
+};
+
+struct Displacer {
+    position: vec3<f32>,
+    radius: f32,
+    strength: f32,
+};
+
+struct Displacers {
+    count: u32,
+    // the length is filled in from the limit of the crate when the shader is loaded
+    displacers: array<Displacer, MAX_GRASS_DISPLACERS>,
+};
+
 @group(2) @binding(0)
 var<uniform> config: ShaderRegionConfig;
+@group(2) @binding(1)
+var<uniform> wind: ShaderWind;
+@group(2) @binding(2)
+var<uniform> displacers: Displacers;
 
 #import bevy_pbr::mesh_functions
+#import bevy_pbr::utils
+#import bevy_pbr::clustered_forward
+#import bevy_pbr::lighting
+#import bevy_pbr::shadows
+#import bevy_pbr::pbr_types
+#import bevy_pbr::pbr_functions
 
 struct Vertex {
     // position of the local vertex in the blade
     @location(0) position: vec3<f32>,
+#ifdef VERTEX_NORMALS
+    // normal of the local vertex in the blade
+    @location(1) normal: vec3<f32>,
+#endif
     // position of the blade as an instance
-    @location(1) position_field_offset: vec3<f32>,
+    @location(2) position_field_offset: vec3<f32>,
     // height of the blade
-    @location(2) height: f32,
+    @location(3) height: f32,
+    // rotation of the blade around the y axis
+    @location(4) rotation: f32,
+    // scale of the blade width
+    @location(5) width: f32,
+    // offset of the blade tip on the xz plane per unit of height
+    @location(6) lean: vec2<f32>,
+    // tint of the main color
+    @location(7) color: vec3<f32>,
+    // normalized direction the blade grows towards
+    @location(8) up: vec3<f32>,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) world_position: vec4<f32>,
+    @location(2) world_normal: vec3<f32>,
 };
 
+struct FragmentInput {
+    @builtin(front_facing) is_front: bool,
+    @builtin(position) frag_coord: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) world_position: vec4<f32>,
+    @location(2) world_normal: vec3<f32>,
+};
+
+
+// rotates `v` around the y axis, turning the x axis towards the z axis
+fn rotate_y(v: vec3<f32>, cos_rotation: f32, sin_rotation: f32) -> vec3<f32> {
+    return vec3<f32>(
+        v.x * cos_rotation - v.z * sin_rotation,
+        v.y,
+        v.x * sin_rotation + v.z * cos_rotation
+    );
+}
+
+// rotates `v` by the rotation which turns the y axis into `up`
+fn rotate_to_up(v: vec3<f32>, up: vec3<f32>) -> vec3<f32> {
+    let cos_angle = up.y;
+    if (cos_angle > 0.9999) {
+        return v;
+    }
+    if (cos_angle < -0.9999) {
+        // upside down, turn around the x axis
+        return vec3<f32>(v.x, -v.y, -v.z);
+    }
+    // axis = normalize(cross(y, up))
+    let axis = normalize(vec3<f32>(up.z, 0., -up.x));
+    let sin_angle = sqrt(1. - cos_angle * cos_angle);
+    return v * cos_angle + cross(axis, v) * sin_angle + axis * dot(axis, v) * (1. - cos_angle);
+}
+
+// a pseudo random value between 0 and 1 for each position on the xz plane
+fn hash(position: vec2<f32>) -> f32 {
+    return fract(sin(dot(position, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+// horizontal offset of a blade tip at `world_position` per unit of height caused by the wind
+fn wind_offset(world_position: vec3<f32>) -> vec2<f32> {
+    let time = globals.time;
+    // gusts travel as waves in the direction of the wind
+    let distance_along = dot(world_position.xz, wind.direction);
+    let gust = sin(distance_along * 0.2 - time * wind.gust_frequency * 6.2831853) * 0.5 + 0.5;
+    // each blade flutters with its own phase
+    let phase = hash(world_position.xz) * 6.2831853;
+    let flutter = vec2<f32>(sin(time * 2.3 + phase), cos(time * 1.7 + phase));
+    return (wind.direction * gust + flutter * wind.turbulence_scale) * wind.strength;
+}
+
+// horizontal offset of a blade tip with its root at `world_position` per unit of height,
+// pushing it away from all displacers around
+fn displacement_offset(world_position: vec3<f32>) -> vec2<f32> {
+    var offset = vec2<f32>(0., 0.);
+    let count = min(displacers.count, MAX_GRASS_DISPLACERS);
+    for (var i = 0u; i < count; i = i + 1u) {
+        let displacer = displacers.displacers[i];
+        let difference = world_position - displacer.position;
+        let displacer_distance = length(difference);
+        let horizontal_distance = length(difference.xz);
+        if (displacer_distance < displacer.radius && horizontal_distance > 0.0001) {
+            let falloff = 1. - displacer_distance / displacer.radius;
+            offset = offset + difference.xz / horizontal_distance * falloff * displacer.strength;
+        }
+    }
+    return offset;
+}
 
 @vertex
 fn vertex(vertex: Vertex) -> VertexOutput {
     var out: VertexOutput;
-    var position = vertex.position.xyz * vec3<f32>(1.,vertex.height, 1.) + vertex.position_field_offset;
+    var position = vertex.position.xyz * vec3<f32>(vertex.width, vertex.height, vertex.width);
+    let cos_rotation = cos(vertex.rotation);
+    let sin_rotation = sin(vertex.rotation);
+    position = rotate_y(position, cos_rotation, sin_rotation);
+    // bend the blade quadratically, so the root stays in place
+    let lean = vertex.lean * vertex.height * vertex.position.y * vertex.position.y;
+    position = position + vec3<f32>(lean.x, 0., lean.y);
+    position = rotate_to_up(position, vertex.up);
+    position = position + vertex.position_field_offset;
+
+    var world_position = mesh_position_local_to_world(mesh.model, vec4<f32>(position, 1.0));
+    // bend quadratically with the height along the blade, so the root stays planted
+    let blade_root = mesh_position_local_to_world(mesh.model, vec4<f32>(vertex.position_field_offset, 1.0));
+    let along_blade = max(vertex.position.y, 0.);
+    let tip_offset = wind_offset(blade_root.xyz) + displacement_offset(blade_root.xyz);
+    let bend = tip_offset * vertex.height * along_blade * along_blade;
+    world_position = world_position + vec4<f32>(bend.x, 0., bend.y, 0.);
+    out.clip_position = mesh_position_world_to_clip(world_position);
+    out.world_position = world_position;
 
-    out.clip_position = mesh_position_local_to_clip(mesh.model, vec4<f32>(position, 1.0));
+#ifdef VERTEX_NORMALS
+    // normals are scaled inversely to the positions
+    let blade_scale = max(vec3<f32>(vertex.width, vertex.height, vertex.width), vec3<f32>(0.0001));
+    var normal = vertex.normal / blade_scale;
+#else
+    // meshes without normals are lit like the ground they grow on
+    var normal = vec3<f32>(0., 1., 0.);
+#endif
+    normal = rotate_y(normal, cos_rotation, sin_rotation);
+    normal = rotate_to_up(normal, vertex.up);
+    out.world_normal = mesh_normal_local_to_world(normal);
 
     let lambda = 1.0 - vertex.position.y / vertex.height;
     let lambda = lambda * lambda * lambda;
-    out.color = mix(config.main_color, config.bottom_color, lambda);
+    let main_color = config.main_color * vec4<f32>(vertex.color, 1.);
+    out.color = mix(main_color, config.bottom_color, lambda);
     return out;
 }
 
 @fragment
-fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
-    return in.color;
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    var pbr_input = pbr_input_new();
+    pbr_input.material.base_color = in.color;
+    // grass is rough and not metallic
+    pbr_input.material.perceptual_roughness = 0.8;
+    pbr_input.material.metallic = 0.;
+    pbr_input.material.reflectance = 0.2;
+    pbr_input.frag_coord = in.frag_coord;
+    pbr_input.world_position = in.world_position;
+    pbr_input.world_normal = prepare_world_normal(in.world_normal, true, in.is_front);
+    pbr_input.N = normalize(pbr_input.world_normal);
+    pbr_input.is_orthographic = view.projection[3].w == 1.0;
+    pbr_input.V = calculate_view(in.world_position, pbr_input.is_orthographic);
+    return pbr(pbr_input);
 }
\ No newline at end of file
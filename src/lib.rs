@@ -6,9 +6,11 @@ use bevy::prelude::*;
 use bevy::render::extract_resource::ExtractResource;
 use bevy::render::render_phase::SetItemPipeline;
 
+pub mod displacement;
 pub mod generator;
 pub mod grass;
 pub mod plugin;
+pub mod streaming;
 
 // Render stuff:
 pub mod cache;
@@ -32,12 +34,14 @@ pub struct GrassBundle {
     /// Also since all elements in [Grass] are instanced together,
     /// it might be more performant to spawn multiple entities each containing locally seperate portions of the grass in the game.
     /// This however, will only be noticable at high number of grassblades.
+    /// Adding a [`GrassChunking`](crate::grass::GrassChunking) component next to the bundle does this automatically.
     pub grass: Grass,
     /// The [`Mesh`] used to render each grassblade.
     ///
     /// The mesh can be changed to however needed,
     /// however note that the lowest vertex of the mesh should be around y=0
     /// in most cases.
+    /// Normals of the mesh are used for the lighting, meshes without normals are lit like the ground.
     pub grass_mesh: Handle<Mesh>,
     #[bundle]
     pub spatial: SpatialBundle,
@@ -69,6 +73,43 @@ impl FromWorld for RegionConfig {
     }
 }
 
+/// The wind moving all grass blades over time.
+///
+/// The bend grows with the height along each blade, so the roots stay in place.
+/// A strength of 0, the default, keeps the grass still
+#[derive(Resource, Clone, Reflect, ExtractResource)]
+#[reflect(Resource)]
+pub struct GrassWind {
+    /// The direction the wind blows towards on the XZ plane
+    pub direction: Vec2,
+    /// How far the blade tips are pushed per unit of blade height
+    pub strength: f32,
+    /// How many gusts pass over a blade per second
+    pub gust_frequency: f32,
+    /// How strong the blades flutter on their own, relative to the strength
+    pub turbulence_scale: f32,
+}
+
+impl Default for GrassWind {
+    fn default() -> Self {
+        GrassWind {
+            direction: Vec2::X,
+            strength: 0.,
+            gust_frequency: 0.3,
+            turbulence_scale: 0.2,
+        }
+    }
+}
+
+impl GrassWind {
+    /// The largest horizontal offset of the blade tips per unit of blade height
+    pub fn max_bend(&self) -> f32 {
+        // the gusts push along the normalized direction, the flutter moves on an ellipse of up to sqrt(2)
+        let gust = self.direction.normalize_or_zero().length();
+        (gust + self.turbulence_scale.abs() * std::f32::consts::SQRT_2) * self.strength.abs()
+    }
+}
+
 pub(crate) type GrassDrawCall = (
     // caches pipeline instead of reinit every call
     SetItemPipeline,
@@ -10,6 +10,14 @@ use bevy::render::render_resource::{
     VertexStepMode,
 };
 use bevy::render::renderer::RenderDevice;
+use bytemuck::Zeroable;
+
+/// The byte offset of a field in [`GrassBlade`]
+macro_rules! blade_offset {
+    ($field:tt) => {
+        bytemuck::offset_of!(GrassBlade::zeroed(), GrassBlade, $field) as u64
+    };
+}
 
 #[derive(Resource)]
 pub struct GrassPipeline {
@@ -35,6 +43,28 @@ impl FromWorld for GrassPipeline {
                     },
                     count: None,
                 },
+                // wind
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::VERTEX,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // displacers
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::VERTEX,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
         });
         let shader = GRASS_SHADER_HANDLE.typed::<Shader>();
@@ -60,6 +90,13 @@ impl SpecializedMeshPipeline for GrassPipeline {
         descriptor.vertex.shader = self.shader.clone();
         let layouts = descriptor.layout.get_or_insert(Vec::new());
         layouts.push(self.region_outline.clone());
+        // only use the attributes of the mesh known by the shader,
+        // so they don't collide with the instance attributes
+        let mut vertex_attributes = vec![Mesh::ATTRIBUTE_POSITION.at_shader_location(0)];
+        if layout.contains(Mesh::ATTRIBUTE_NORMAL) {
+            vertex_attributes.push(Mesh::ATTRIBUTE_NORMAL.at_shader_location(1));
+        }
+        descriptor.vertex.buffers[0] = layout.get_layout(&vertex_attributes)?;
         descriptor.vertex.buffers.push(VertexBufferLayout {
             array_stride: std::mem::size_of::<GrassBlade>() as u64,
             step_mode: VertexStepMode::Instance,
@@ -67,14 +104,44 @@ impl SpecializedMeshPipeline for GrassPipeline {
                 // position of the mesh as instance
                 VertexAttribute {
                     format: VertexFormat::Float32x3,
-                    offset: 0,
-                    shader_location: 1, // 0 is reserved for the position of the mesh
+                    offset: blade_offset!(position),
+                    shader_location: 2, // 0 and 1 are reserved for the position and normal of the mesh
                 },
                 // height scale
                 VertexAttribute {
                     format: VertexFormat::Float32,
-                    offset: VertexFormat::Float32x3.size(),
-                    shader_location: 2,
+                    offset: blade_offset!(height),
+                    shader_location: 3,
+                },
+                // rotation around the y axis
+                VertexAttribute {
+                    format: VertexFormat::Float32,
+                    offset: blade_offset!(rotation),
+                    shader_location: 4,
+                },
+                // width scale
+                VertexAttribute {
+                    format: VertexFormat::Float32,
+                    offset: blade_offset!(width),
+                    shader_location: 5,
+                },
+                // lean of the tip on the xz plane
+                VertexAttribute {
+                    format: VertexFormat::Float32x2,
+                    offset: blade_offset!(lean),
+                    shader_location: 6,
+                },
+                // color tint
+                VertexAttribute {
+                    format: VertexFormat::Float32x3,
+                    offset: blade_offset!(color),
+                    shader_location: 7,
+                },
+                // growth direction
+                VertexAttribute {
+                    format: VertexFormat::Float32x3,
+                    offset: blade_offset!(up),
+                    shader_location: 8,
                 },
             ],
         });
@@ -1,8 +1,13 @@
-use crate::cache::GrassCache;
-use crate::grass::add_aabb_box_to_grass;
+use crate::cache::{GrassBufferPool, GrassCache, GrassUniformBindGroup};
+use crate::displacement::{ExtractedGrassDisplacers, GrassDisplacer, MAX_GRASS_DISPLACERS};
+use crate::generator::task::{
+    poll_grass_generation_tasks, GrassGenerated, GrassGenerationProgress,
+};
+use crate::grass::{add_aabb_box_to_grass, split_grass_into_chunks};
 use crate::pipeline::GrassPipeline;
+use crate::streaming::stream_grass;
 use crate::GrassDrawCall;
-use crate::{extract, prepare, queue, RegionConfig};
+use crate::{extract, prepare, queue, GrassWind, RegionConfig};
 use bevy::asset::load_internal_asset;
 use bevy::core_pipeline::core_3d::Opaque3d;
 use bevy::prelude::*;
@@ -26,7 +31,7 @@ pub const GRASS_MESH_HANDLE: HandleUntyped =
 impl Plugin for GrassPlugin {
     fn build(&self, app: &mut App) {
         // Load grass shader into cache
-        load_internal_asset!(app, GRASS_SHADER_HANDLE, "grass.wgsl", Shader::from_wgsl);
+        load_internal_asset!(app, GRASS_SHADER_HANDLE, "grass.wgsl", grass_shader);
 
         // Load default grass mesh
         let mut meshes = app.world.resource_mut::<Assets<Mesh>>();
@@ -34,38 +39,77 @@ impl Plugin for GrassPlugin {
         // Init resources
         app.init_resource::<RegionConfig>()
             .register_type::<RegionConfig>()
-            .add_system(add_aabb_box_to_grass);
+            .init_resource::<GrassWind>()
+            .register_type::<GrassWind>()
+            .register_type::<GrassDisplacer>()
+            .init_resource::<GrassGenerationProgress>()
+            .add_event::<GrassGenerated>()
+            // streaming despawns chunks, so all other commands on them have to be applied before
+            .add_system(poll_grass_generation_tasks.before(stream_grass))
+            .add_system(add_aabb_box_to_grass.before(stream_grass))
+            // the aabb of the chunked entity is removed again
+            .add_system(
+                split_grass_into_chunks
+                    .after(add_aabb_box_to_grass)
+                    .before(stream_grass),
+            )
+            .add_system(stream_grass);
         // Add extraction
-        app.add_plugin(ExtractResourcePlugin::<RegionConfig>::default());
+        app.add_plugin(ExtractResourcePlugin::<RegionConfig>::default())
+            .add_plugin(ExtractResourcePlugin::<GrassWind>::default());
         // Init render app
         app.sub_app_mut(RenderApp)
             .add_render_command::<Opaque3d, GrassDrawCall>()
             .init_resource::<FallbackImage>()
             .init_resource::<GrassPipeline>()
             .init_resource::<GrassCache>()
+            .init_resource::<GrassBufferPool>()
+            .init_resource::<GrassUniformBindGroup>()
+            .init_resource::<ExtractedGrassDisplacers>()
             .init_resource::<SpecializedMeshPipelines<GrassPipeline>>()
             .add_system_to_stage(RenderStage::Extract, extract::extract_grass)
+            .add_system_to_stage(RenderStage::Extract, extract::extract_grass_displacers)
             .add_system_to_stage(RenderStage::Prepare, prepare::prepare_uniform_buffers)
             .add_system_to_stage(
                 RenderStage::Prepare,
                 prepare::prepare_instance_buffer.after(prepare::prepare_uniform_buffers),
             )
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                prepare::prepare_displacer_buffer.after(prepare::prepare_uniform_buffers),
+            )
             .add_system_to_stage(RenderStage::Queue, queue::queue_grass_buffers);
     }
 }
 
+/// Creates the grass shader with the limits of the crate filled in
+fn grass_shader(source: &str) -> Shader {
+    // shader defs can't carry values, so the array length is replaced in the source
+    let max_displacers = format!("{MAX_GRASS_DISPLACERS}u");
+    Shader::from_wgsl(source.replace("MAX_GRASS_DISPLACERS", &max_displacers))
+}
+
 /// Constructs the default look of the grass, as shown in the examples
 fn default_grass_mesh() -> Mesh {
+    let positions = vec![
+        [0., 0., 0.],
+        [0.5, 0., 0.],
+        [0.25, 0., 0.4],
+        [0.25, 1., 0.15],
+    ];
+    // rounded normals point away from the center line of the blade instead of along the flat faces,
+    // so the thin blades are shaded like round stalks and the tip like the ground
+    let center = Vec3::new(0.25, 0., 0.4 / 3.);
+    let normals: Vec<[f32; 3]> = positions
+        .iter()
+        .map(|&[x, y, z]| {
+            let outward = Vec3::new(x - center.x, 0., z - center.z).normalize_or_zero();
+            (outward * (1. - y) + Vec3::Y * 0.3).normalize().to_array()
+        })
+        .collect();
     let mut grass_mesh = Mesh::new(PrimitiveTopology::TriangleList);
-    grass_mesh.insert_attribute(
-        Mesh::ATTRIBUTE_POSITION,
-        vec![
-            [0., 0., 0.],
-            [0.5, 0., 0.],
-            [0.25, 0., 0.4],
-            [0.25, 1., 0.15],
-        ],
-    );
+    grass_mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    grass_mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
     grass_mesh.set_indices(Some(Indices::U32(vec![1, 0, 3, 2, 1, 3, 0, 2, 3])));
     grass_mesh
 }
@@ -1,66 +1,170 @@
-use crate::cache::GrassCache;
+use crate::cache::{GrassBufferPool, GrassCache, GrassUniformBindGroup};
+use crate::displacement::{ExtractedGrassDisplacers, MAX_GRASS_DISPLACERS};
+use crate::grass::GrassBlade;
 use crate::pipeline::GrassPipeline;
-use crate::RegionConfig;
+use crate::{GrassWind, RegionConfig};
 use bevy::prelude::*;
 use bevy::render::render_resource::{
-    BindGroupDescriptor, BindGroupEntry, BindingResource, BufferBinding, BufferInitDescriptor,
-    BufferUsages, ShaderType,
+    BindGroupDescriptor, BindGroupEntry, BindingResource, BufferBinding, BufferDescriptor,
+    BufferInitDescriptor, BufferUsages, ShaderType,
 };
-use bevy::render::renderer::RenderDevice;
+use bevy::render::renderer::{RenderDevice, RenderQueue};
 use bytemuck::{Pod, Zeroable};
 
-pub fn prepare_instance_buffer(mut cache: ResMut<GrassCache>, render_device: Res<RenderDevice>) {
+/// Uploads the instances of all dirty chunks, leaving the buffers of unchanged chunks untouched.
+///
+/// Buffers which are large enough are rewritten in place,
+/// new buffers are allocated with some headroom, so growing chunks don't need a new buffer on every change.
+pub fn prepare_instance_buffer(
+    mut cache: ResMut<GrassCache>,
+    mut buffer_pool: ResMut<GrassBufferPool>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
     if !cache.is_changed() {
         return;
     }
-    for instance_data in cache.values_mut() {
-        let entity_buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
-            label: Some("Instance entity buffer"),
-            contents: bytemuck::cast_slice(&instance_data.grass.instances.as_slice()),
-            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
-        });
+    for instance_data in cache.values_mut().filter(|chunk| chunk.dirty) {
+        instance_data.dirty = false;
+        let contents: &[u8] = bytemuck::cast_slice(instance_data.grass.instances.as_slice());
+        let current_buffer = instance_data.buffer.take();
+        if contents.is_empty() {
+            // empty chunks are not drawn
+            if let Some(buffer) = current_buffer {
+                buffer_pool.recycle(buffer);
+            }
+            continue;
+        }
+        let size = contents.len() as u64;
+        // reuse the current buffer or a recycled one if it is large enough
+        let reused_buffer = match current_buffer {
+            Some(buffer) if buffer.size() >= size => Some(buffer),
+            too_small => {
+                if let Some(buffer) = too_small {
+                    buffer_pool.recycle(buffer);
+                }
+                buffer_pool.take(size)
+            }
+        };
+        let entity_buffer = match reused_buffer {
+            Some(buffer) => buffer,
+            None => render_device.create_buffer(&BufferDescriptor {
+                label: Some("Instance entity buffer"),
+                size: grown_buffer_size(size),
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        };
+        render_queue.write_buffer(&entity_buffer, 0, contents);
         instance_data.buffer = Some(entity_buffer);
     }
 }
 
+/// The size of a new instance buffer holding `size` bytes, with room for a quarter more blades
+fn grown_buffer_size(size: u64) -> u64 {
+    // growing by whole blades keeps the size aligned for copies
+    let blade_size = std::mem::size_of::<GrassBlade>() as u64;
+    let blades = size / blade_size;
+    (blades + blades / 4) * blade_size
+}
+
 pub fn prepare_uniform_buffers(
     pipeline: Res<GrassPipeline>,
-    mut cache: ResMut<GrassCache>,
+    mut uniform_bind_group: ResMut<GrassUniformBindGroup>,
     region_config: Res<RegionConfig>,
+    wind: Res<GrassWind>,
     render_device: Res<RenderDevice>,
 ) {
-    if !region_config.is_changed() {
-        return;
-    }
+    if region_config.is_changed() || wind.is_changed() || uniform_bind_group.bind_group.is_none() {
+        let shader_config = ShaderRegionConfig::from(region_config.as_ref());
+        let color_buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("Config"),
+            contents: bytemuck::bytes_of(&shader_config),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let shader_wind = ShaderWind::from(wind.as_ref());
+        let wind_buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("Wind"),
+            contents: bytemuck::bytes_of(&shader_wind),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
 
-    let shader_config = ShaderRegionConfig::from(region_config.as_ref());
-    let color_buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
-        label: Some("Config"),
-        contents: bytemuck::bytes_of(&shader_config),
-        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-    });
+        let displacer_buffer = uniform_bind_group
+            .displacer_buffer
+            .get_or_insert_with(|| {
+                render_device.create_buffer(&BufferDescriptor {
+                    label: Some("Displacers"),
+                    size: std::mem::size_of::<ShaderDisplacers>() as u64,
+                    usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                })
+            })
+            .clone();
 
-    let layout = pipeline.region_outline.clone();
-    let bind_group_descriptor = BindGroupDescriptor {
-        label: Some("Grass uniform bind group"),
-        layout: &layout,
-        entries: &[
-            // config
-            BindGroupEntry {
-                binding: 0,
-                resource: BindingResource::Buffer(BufferBinding {
-                    buffer: &color_buffer,
-                    offset: 0,
-                    size: None,
-                }),
-            },
-        ],
-    };
-    let bind_group = render_device.create_bind_group(&bind_group_descriptor);
+        let layout = pipeline.region_outline.clone();
+        let bind_group_descriptor = BindGroupDescriptor {
+            label: Some("Grass uniform bind group"),
+            layout: &layout,
+            entries: &[
+                // config
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Buffer(BufferBinding {
+                        buffer: &color_buffer,
+                        offset: 0,
+                        size: None,
+                    }),
+                },
+                // wind
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Buffer(BufferBinding {
+                        buffer: &wind_buffer,
+                        offset: 0,
+                        size: None,
+                    }),
+                },
+                // displacers
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Buffer(BufferBinding {
+                        buffer: &displacer_buffer,
+                        offset: 0,
+                        size: None,
+                    }),
+                },
+            ],
+        };
+        uniform_bind_group.bind_group =
+            Some(render_device.create_bind_group(&bind_group_descriptor));
+    }
+}
 
-    for instance_data in cache.values_mut() {
-        instance_data.uniform_bind_ground = Some(bind_group.clone());
+/// Uploads the displacers of the current frame
+pub fn prepare_displacer_buffer(
+    displacers: Res<ExtractedGrassDisplacers>,
+    uniform_bind_group: Res<GrassUniformBindGroup>,
+    render_queue: Res<RenderQueue>,
+) {
+    let buffer = match &uniform_bind_group.displacer_buffer {
+        Some(buffer) => buffer,
+        None => return,
+    };
+    let mut shader_displacers = ShaderDisplacers::zeroed();
+    for (shader_displacer, displacer) in shader_displacers
+        .displacers
+        .iter_mut()
+        .zip(displacers.iter())
+    {
+        *shader_displacer = ShaderDisplacer {
+            position: displacer.position,
+            radius: displacer.radius,
+            strength: displacer.strength,
+            _padding: [0.; 3],
+        };
     }
+    shader_displacers.count = displacers.len().min(MAX_GRASS_DISPLACERS) as u32;
+    render_queue.write_buffer(buffer, 0, bytemuck::bytes_of(&shader_displacers));
 }
 
 #[derive(Debug, Clone, Copy, Pod, Zeroable, ShaderType)]
@@ -73,8 +177,50 @@ struct ShaderRegionConfig {
 impl From<&RegionConfig> for ShaderRegionConfig {
     fn from(config: &RegionConfig) -> Self {
         Self {
-            main_color: config.main_color.into(),
-            bottom_color: config.bottom_color.into(),
+            main_color: config.main_color.as_linear_rgba_f32().into(),
+            bottom_color: config.bottom_color.as_linear_rgba_f32().into(),
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct ShaderWind {
+    direction: Vec2,
+    strength: f32,
+    gust_frequency: f32,
+    turbulence_scale: f32,
+    // uniform buffers are padded to 16 bytes
+    _padding: [f32; 3],
+}
+
+impl From<&GrassWind> for ShaderWind {
+    fn from(wind: &GrassWind) -> Self {
+        Self {
+            direction: wind.direction.normalize_or_zero(),
+            strength: wind.strength,
+            gust_frequency: wind.gust_frequency,
+            turbulence_scale: wind.turbulence_scale,
+            _padding: [0.; 3],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct ShaderDisplacer {
+    position: Vec3,
+    radius: f32,
+    strength: f32,
+    // array elements of uniform buffers are aligned to 16 bytes
+    _padding: [f32; 3],
+}
+
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct ShaderDisplacers {
+    count: u32,
+    // the array starts at the next 16 bytes
+    _padding: [u32; 3],
+    displacers: [ShaderDisplacer; MAX_GRASS_DISPLACERS],
+}
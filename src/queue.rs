@@ -36,9 +36,18 @@ pub fn queue_grass_buffers(
             if let Some(mesh) = meshes.get(mesh_handle) {
                 let key =
                     view_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology);
-                let pipeline = pipelines
-                    .specialize(&mut pipeline_cache, &grass_pipeline, key, &mesh.layout)
-                    .unwrap();
+                let pipeline = match pipelines.specialize(
+                    &mut pipeline_cache,
+                    &grass_pipeline,
+                    key,
+                    &mesh.layout,
+                ) {
+                    Ok(pipeline) => pipeline,
+                    Err(err) => {
+                        error!("{}", err);
+                        continue;
+                    }
+                };
                 transparent_phase.add(Opaque3d {
                     distance: rangefinder.distance(&mesh_uniform.transform),
                     pipeline,
@@ -1,4 +1,4 @@
-use crate::cache::GrassCache;
+use crate::cache::{GrassCache, GrassUniformBindGroup};
 use bevy::ecs::system::lifetimeless::{Read, SQuery, SRes};
 use bevy::ecs::system::SystemParamItem;
 use bevy::prelude::*;
@@ -12,6 +12,7 @@ impl EntityRenderCommand for DrawMeshInstanced {
     type Param = (
         SRes<RenderAssets<Mesh>>,
         SRes<GrassCache>,
+        SRes<GrassUniformBindGroup>,
         SQuery<Read<Handle<Mesh>>>,
     );
 
@@ -19,24 +20,38 @@ impl EntityRenderCommand for DrawMeshInstanced {
     fn render<'w>(
         _view: Entity,
         item: Entity,
-        (meshes, cache, mesh_query): SystemParamItem<'w, '_, Self::Param>,
+        (meshes, cache, uniform_bind_group, mesh_query): SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
-        let mesh_handle = mesh_query.get(item).unwrap();
-        let gpu_mesh = match meshes.into_inner().get(mesh_handle) {
+        let gpu_mesh = match mesh_query
+            .get(item)
+            .ok()
+            .and_then(|mesh_handle| meshes.into_inner().get(mesh_handle))
+        {
             Some(mesh) => mesh,
             None => return RenderCommandResult::Failure,
         };
-        if !cache.contains_key(&item) {
-            return RenderCommandResult::Failure;
+        let chunk = match cache.into_inner().get(&item) {
+            Some(chunk) => chunk,
+            None => return RenderCommandResult::Failure,
+        };
+        let grass_blade_count = chunk.grass.instances.len() as u32;
+        if grass_blade_count == 0 {
+            // nothing to draw, empty chunks have no instance buffer
+            return RenderCommandResult::Success;
         }
-        let chunk = cache.into_inner().get(&item).unwrap();
+
+        // the buffers are missing until the chunk is prepared
+        let (bind_group, instance_buffer) =
+            match (&uniform_bind_group.into_inner().bind_group, &chunk.buffer) {
+                (Some(bind_group), Some(buffer)) => (bind_group, buffer),
+                _ => return RenderCommandResult::Failure,
+            };
 
         // set uniform
-        pass.set_bind_group(2, chunk.uniform_bind_ground.as_ref().unwrap(), &[]);
+        pass.set_bind_group(2, bind_group, &[]);
         pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
-        pass.set_vertex_buffer(1, chunk.buffer.as_ref().unwrap().slice(..));
-        let grass_blade_count = chunk.grass.instances.len() as u32;
+        pass.set_vertex_buffer(1, instance_buffer.slice(..));
         match &gpu_mesh.buffer_info {
             GpuBufferInfo::Indexed {
                 buffer,
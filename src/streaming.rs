@@ -0,0 +1,147 @@
+use crate::generator::task::GrassGenerationTask;
+use crate::generator::{mix_seed, GrassGenerator, StandardGeneratorConfig};
+use crate::GrassBundle;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Generates grass chunks on demand in a ring of cells around every [`GrassStreamingTarget`].
+///
+/// Chunks leaving the ring are despawned and their gpu buffers are reused for new chunks.
+/// Each chunk is generated in world space with a seed derived from its cell coordinates,
+/// so revisiting an area shows the same grass and fields, exclusions and placement rules
+/// continue seamlessly over the cell borders.
+/// Insert this resource to start streaming.
+#[derive(Resource)]
+pub struct GrassStreaming {
+    /// The size of the square cells on the XZ plane
+    pub cell_size: f32,
+    /// The radius of the ring around the targets in cells
+    pub radius: u32,
+    /// The config used for each chunk.
+    ///
+    /// The seed of each chunk is derived from this seed and the cell coordinates,
+    /// if [None] is used, 0 is used as base seed.
+    /// Noise fields without a seed of their own are seeded with the base seed,
+    /// so their patches continue over the cell borders
+    pub config: StandardGeneratorConfig,
+    /// The [`Mesh`] used to render each grassblade
+    pub grass_mesh: Handle<Mesh>,
+    generator: Arc<dyn Fn(Vec2) -> ChunkGenerator + Send + Sync>,
+    chunks: HashMap<IVec2, Entity>,
+}
+
+impl GrassStreaming {
+    /// Creates a streaming configuration.
+    ///
+    /// `generator` creates the generator of a cell from the center of the cell on the XZ plane.
+    /// The generator should cover an area of `cell_size` around this center in world space,
+    /// for example a [`Plane`](crate::generator::plane::Plane) translated to the center.
+    pub fn new<F, G>(
+        generator: F,
+        cell_size: f32,
+        radius: u32,
+        config: StandardGeneratorConfig,
+    ) -> Self
+    where
+        F: Fn(Vec2) -> G + Send + Sync + 'static,
+        G: GrassGenerator<StandardGeneratorConfig> + Send + Sync + 'static,
+    {
+        GrassStreaming {
+            cell_size,
+            radius,
+            config,
+            grass_mesh: GrassBundle::default().grass_mesh,
+            generator: Arc::new(move |center| Arc::new(generator(center)) as ChunkGenerator),
+            chunks: HashMap::default(),
+        }
+    }
+    /// The cell containing `position`
+    pub fn cell_at(&self, position: Vec3) -> IVec2 {
+        (Vec2::new(position.x, position.z) / self.cell_size)
+            .floor()
+            .as_ivec2()
+    }
+    /// The chunk entities which are currently spawned, by their cell
+    pub fn chunks(&self) -> &HashMap<IVec2, Entity> {
+        &self.chunks
+    }
+    /// The deterministic seed of the chunk in `cell`
+    pub fn chunk_seed(&self, cell: IVec2) -> u64 {
+        let cell = ((cell.x as u32 as u64) << 32) | cell.y as u32 as u64;
+        mix_seed(self.config.seed.unwrap_or(0), cell)
+    }
+}
+
+type ChunkGenerator = Arc<dyn GrassGenerator<StandardGeneratorConfig> + Send + Sync>;
+
+/// Marks an entity, usually the camera, around which grass is streamed by [`GrassStreaming`]
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct GrassStreamingTarget;
+
+/// Marks a chunk spawned by [`GrassStreaming`]
+#[derive(Component, Debug, Clone, Copy)]
+pub struct StreamedGrassChunk {
+    pub cell: IVec2,
+}
+
+pub(crate) fn stream_grass(
+    mut commands: Commands,
+    streaming: Option<ResMut<GrassStreaming>>,
+    targets: Query<&GlobalTransform, With<GrassStreamingTarget>>,
+) {
+    let mut streaming = match streaming {
+        Some(streaming) => streaming,
+        None => return,
+    };
+    let radius = streaming.radius as i32;
+    let mut wanted = HashSet::default();
+    for target in targets.iter() {
+        let center = streaming.cell_at(target.translation());
+        for x in -radius..=radius {
+            for z in -radius..=radius {
+                if x * x + z * z <= radius * radius {
+                    wanted.insert(center + IVec2::new(x, z));
+                }
+            }
+        }
+    }
+    let unchanged = wanted.len() == streaming.chunks.len()
+        && streaming.chunks.keys().all(|cell| wanted.contains(cell));
+    if unchanged {
+        return;
+    }
+
+    streaming.chunks.retain(|cell, entity| {
+        let keep = wanted.contains(cell);
+        if !keep {
+            // the chunk might have been despawned already, for example together with its parent
+            if let Some(entity_commands) = commands.get_entity(*entity) {
+                entity_commands.despawn_recursive();
+            }
+        }
+        keep
+    });
+    for cell in wanted {
+        if streaming.chunks.contains_key(&cell) {
+            continue;
+        }
+        let mut config = streaming.config.clone();
+        config.fix_field_seeds(streaming.config.seed.unwrap_or(0));
+        config.seed = Some(streaming.chunk_seed(cell));
+        let center = (cell.as_vec2() + 0.5) * streaming.cell_size;
+        // the blades are generated in world space, so the chunk stays at the origin
+        let generator = (streaming.generator)(center);
+        let entity = commands
+            .spawn((
+                GrassBundle {
+                    grass_mesh: streaming.grass_mesh.clone(),
+                    ..default()
+                },
+                GrassGenerationTask::new(generator, config),
+                StreamedGrassChunk { cell },
+            ))
+            .id();
+        streaming.chunks.insert(cell, entity);
+    }
+}